@@ -0,0 +1,126 @@
+//! Where asset files are read from.
+//!
+//! Debug builds on native read straight from the `assets/` directory and watch modification times,
+//! so assets can be edited while the game is running. Release and wasm builds embed every asset.
+
+use std::borrow::Cow;
+
+pub use source::AssetSource;
+
+#[cfg(all(debug_assertions, not(target_arch = "wasm32")))]
+mod source {
+    use std::{
+        borrow::Cow,
+        cell::RefCell,
+        collections::HashMap,
+        path::{Path, PathBuf},
+        time::SystemTime,
+    };
+
+    pub struct AssetSource {
+        root: PathBuf,
+        /// Modification time of every file that has been read, by path.
+        modified: RefCell<HashMap<String, SystemTime>>,
+    }
+    impl Default for AssetSource {
+        fn default() -> Self {
+            Self {
+                root: Path::new(env!("CARGO_MANIFEST_DIR")).join("assets"),
+                modified: RefCell::new(HashMap::new()),
+            }
+        }
+    }
+    impl AssetSource {
+        pub fn read(&self, path: &str) -> Option<Cow<'static, [u8]>> {
+            let full_path = self.root.join(path);
+            let bytes = std::fs::read(&full_path).ok()?;
+            if let Ok(modified) = full_path.metadata().and_then(|f| f.modified()) {
+                self.modified
+                    .borrow_mut()
+                    .insert(path.to_string(), modified);
+            }
+            Some(Cow::Owned(bytes))
+        }
+        /// Overwrites the file at `path`, like when saving a level from the editor.
+        pub fn write(&self, path: &str, contents: &str) -> Result<(), String> {
+            std::fs::write(self.root.join(path), contents).map_err(|err| err.to_string())
+        }
+        /// Paths of previously read files that have been modified since they were last read.
+        pub fn changed(&self) -> Vec<String> {
+            let mut changed = Vec::new();
+            for (path, last_modified) in self.modified.borrow_mut().iter_mut() {
+                let Ok(modified) = self.root.join(path).metadata().and_then(|f| f.modified())
+                else {
+                    continue;
+                };
+                if modified > *last_modified {
+                    *last_modified = modified;
+                    changed.push(path.clone());
+                }
+            }
+            changed
+        }
+    }
+}
+
+#[cfg(not(all(debug_assertions, not(target_arch = "wasm32"))))]
+mod source {
+    use std::borrow::Cow;
+
+    macro_rules! embed {
+        ($($path:literal),* $(,)?) => {
+            &[$(($path, include_bytes!(concat!("../assets/", $path)))),*]
+        };
+    }
+
+    const EMBEDDED: &[(&str, &[u8])] = embed![
+        "font.ase",
+        "tileset.ase",
+        "poi.ase",
+        "pumpkin_guy.ase",
+        "win_screen.ase",
+        "player_body.ase",
+        "player_carry.ase",
+        "player_walk.ase",
+        "player_idle.ase",
+        "world/world.tmx",
+        "world/world.tsx",
+        "world/cellar.tmx",
+        "physics.toml",
+    ];
+
+    pub struct AssetSource {
+        files: &'static [(&'static str, &'static [u8])],
+    }
+    impl Default for AssetSource {
+        fn default() -> Self {
+            Self { files: EMBEDDED }
+        }
+    }
+    impl AssetSource {
+        pub fn read(&self, path: &str) -> Option<Cow<'static, [u8]>> {
+            self.files
+                .iter()
+                .find(|(name, _)| *name == path)
+                .map(|(_, bytes)| Cow::Borrowed(*bytes))
+        }
+        pub fn write(&self, _path: &str, _contents: &str) -> Result<(), String> {
+            Err("assets are embedded in this build".to_string())
+        }
+        /// Embedded assets never change.
+        pub fn changed(&self) -> Vec<String> {
+            Vec::new()
+        }
+    }
+}
+
+impl AssetSource {
+    /// Reads an asset that the game can't run without.
+    pub fn expect(&self, path: &str) -> Cow<'static, [u8]> {
+        self.read(path)
+            .unwrap_or_else(|| panic!("missing asset \"{path}\""))
+    }
+    pub fn read_string(&self, path: &str) -> Option<String> {
+        String::from_utf8(self.read(path)?.into_owned()).ok()
+    }
+}
@@ -1,9 +1,19 @@
-use asefile::AsepriteFile;
+use asefile::{AsepriteFile, AsepriteParseError};
 use hashmap_macro::hashmap;
 use image::EncodableLayout;
 use macroquad::prelude::*;
 
-use crate::{physics::update_physicsbody, utils::*};
+use std::collections::HashMap;
+
+use crate::{
+    asset_source::AssetSource,
+    physics::{Hitbox, PhysicsConfig, TileShape, Tiles, update_physicsbody},
+    tiled::{
+        GID_MASK, MapUpdate, Properties, TileChunk, TileLayer, TiledError, Tileset, parse_map,
+        update_map,
+    },
+    utils::*,
+};
 
 pub struct Assets {
     font: Spritesheet,
@@ -11,24 +21,45 @@ pub struct Assets {
     pub poi: Animation,
     pub pumpkin_guy_animation: Animation,
     pub win_screen: Texture2D,
+    pub player_body: Animation,
+    pub player_carry: Animation,
+    pub player_walk: Animation,
+    pub player_idle: Animation,
 }
-impl Default for Assets {
-    fn default() -> Self {
+impl Assets {
+    pub fn new(source: &AssetSource) -> Self {
         Self {
-            font: Spritesheet::new(
-                load_ase_texture(include_bytes!("../assets/font.ase"), None),
-                4.0,
-            ),
-            tileset: Spritesheet::new(
-                load_ase_texture(include_bytes!("../assets/tileset.ase"), None),
-                8.0,
-            ),
-            poi: Animation::from_file(include_bytes!("../assets/poi.ase")),
-            pumpkin_guy_animation: Animation::from_file(include_bytes!(
-                "../assets/pumpkin_guy.ase"
-            )),
-            win_screen: load_ase_texture(include_bytes!("../assets/win_screen.ase"), None),
+            font: Spritesheet::new(load_ase_texture(&source.expect("font.ase"), None), 4.0),
+            tileset: Spritesheet::new(load_ase_texture(&source.expect("tileset.ase"), None), 8.0),
+            poi: Animation::from_file(&source.expect("poi.ase")),
+            pumpkin_guy_animation: Animation::from_file(&source.expect("pumpkin_guy.ase")),
+            win_screen: load_ase_texture(&source.expect("win_screen.ase"), None),
+            player_body: Animation::from_file(&source.expect("player_body.ase")),
+            player_carry: Animation::from_file(&source.expect("player_carry.ase")),
+            player_walk: Animation::from_file(&source.expect("player_walk.ase")),
+            player_idle: Animation::from_file(&source.expect("player_idle.ase")),
+        }
+    }
+    /// Reloads the asset at `path` in place. Returns false if `path` isn't one of these assets.
+    pub fn reload(&mut self, source: &AssetSource, path: &str) -> bool {
+        let read = || source.read(path).unwrap_or_default();
+        match path {
+            "font.ase" => self.font.reload(&read()),
+            "tileset.ase" => self.tileset.reload(&read()),
+            "poi.ase" => self.poi.reload(&read()),
+            "pumpkin_guy.ase" => self.pumpkin_guy_animation.reload(&read()),
+            "player_body.ase" => self.player_body.reload(&read()),
+            "player_carry.ase" => self.player_carry.reload(&read()),
+            "player_walk.ase" => self.player_walk.reload(&read()),
+            "player_idle.ase" => self.player_idle.reload(&read()),
+            "win_screen.ase" => {
+                if let Some(texture) = reload_ase_texture(&read()) {
+                    self.win_screen = texture;
+                }
+            }
+            _ => return false,
         }
+        true
     }
 }
 impl Assets {
@@ -74,7 +105,10 @@ impl Assets {
     }
 }
 fn load_ase_texture(bytes: &[u8], layer: Option<u32>) -> Texture2D {
-    let img = AsepriteFile::read(bytes).unwrap();
+    try_load_ase_texture(bytes, layer).unwrap()
+}
+fn try_load_ase_texture(bytes: &[u8], layer: Option<u32>) -> Result<Texture2D, AsepriteParseError> {
+    let img = AsepriteFile::read(bytes)?;
     let img = if let Some(layer) = layer {
         img.layer(layer).frame(0).image()
     } else {
@@ -87,7 +121,13 @@ fn load_ase_texture(bytes: &[u8], layer: Option<u32>) -> Texture2D {
     };
     let texture = Texture2D::from_image(&new);
     texture.set_filter(FilterMode::Nearest);
-    texture
+    Ok(texture)
+}
+/// Loads a texture for hot reloading, where a file that's still being written shouldn't crash the game.
+fn reload_ase_texture(bytes: &[u8]) -> Option<Texture2D> {
+    try_load_ase_texture(bytes, None)
+        .inspect_err(|err| error!("couldn't reload texture: {err}"))
+        .ok()
 }
 
 pub struct Spritesheet {
@@ -101,6 +141,11 @@ impl Spritesheet {
             sprite_size,
         }
     }
+    pub fn reload(&mut self, bytes: &[u8]) {
+        if let Some(texture) = reload_ase_texture(bytes) {
+            self.texture = texture;
+        }
+    }
     /// Same as `draw_tile`, except centered
     pub fn draw_sprite(
         &self,
@@ -126,6 +171,18 @@ impl Spritesheet {
         tile_x: f32,
         tile_y: f32,
         params: Option<&DrawTextureParams>,
+    ) {
+        self.draw_tile_ex(screen_x, screen_y, tile_x, tile_y, WHITE, params);
+    }
+    /// Same as `draw_tile`, except tinted with `color`
+    pub fn draw_tile_ex(
+        &self,
+        screen_x: f32,
+        screen_y: f32,
+        tile_x: f32,
+        tile_y: f32,
+        color: Color,
+        params: Option<&DrawTextureParams>,
     ) {
         let mut p = params.cloned().unwrap_or(DrawTextureParams::default());
         p.dest_size = p
@@ -137,7 +194,7 @@ impl Spritesheet {
             w: self.sprite_size,
             h: self.sprite_size,
         }));
-        draw_texture_ex(&self.texture, screen_x, screen_y, WHITE, p);
+        draw_texture_ex(&self.texture, screen_x, screen_y, color, p);
     }
 }
 
@@ -147,7 +204,10 @@ pub struct Animation {
 }
 impl Animation {
     pub fn from_file(bytes: &[u8]) -> Self {
-        let ase = AsepriteFile::read(bytes).unwrap();
+        Self::try_from_file(bytes).unwrap()
+    }
+    fn try_from_file(bytes: &[u8]) -> Result<Self, AsepriteParseError> {
+        let ase = AsepriteFile::read(bytes)?;
         let mut frames = Vec::new();
         let mut total_length = 0;
         for index in 0..ase.num_frames() {
@@ -163,9 +223,15 @@ impl Animation {
             let texture = Texture2D::from_image(&new);
             frames.push((texture, duration));
         }
-        Self {
+        Ok(Self {
             frames,
             total_length,
+        })
+    }
+    pub fn reload(&mut self, bytes: &[u8]) {
+        match Self::try_from_file(bytes) {
+            Ok(animation) => *self = animation,
+            Err(err) => error!("couldn't reload animation: {err}"),
         }
     }
     pub fn get_at_time(&self, mut time: u32) -> &Texture2D {
@@ -183,26 +249,62 @@ impl Animation {
 
 pub struct Pumpkin {
     pub pos: Vec2,
+    /// Position at the start of the last tick, for interpolating between ticks.
+    pub last_pos: Vec2,
     pub velocity: Vec2,
     pub on_ground: bool,
+    /// Friction of whatever the pumpkin is resting on.
+    pub friction: f32,
+    /// Whether the pumpkin was let go of and hasn't left the player yet. It and the player pass
+    /// through each other until then, so dropping it puts it at the player's feet.
+    pub through_player: bool,
+    /// Id of the map object the pumpkin was loaded from, or added as in the editor. `None` for
+    /// pumpkins that aren't in the map, like ones spawned from the console.
+    pub id: Option<u32>,
 }
 impl Pumpkin {
-    pub fn update(&mut self, delta_time: f32, collision_tiles: &[Chunk], one_way_tiles: &[Chunk]) {
-        self.velocity.y += GRAVITY * delta_time;
-        self.velocity.x -=
-            self.velocity.x * if self.on_ground { GROUND_FRICTION } else { 0.0 } * delta_time;
+    pub const HITBOX: Hitbox = Hitbox::new(0.0, 0.0, 8.0, 8.0);
+    /// A pumpkin resting at `pos`.
+    pub fn new(pos: Vec2) -> Self {
+        Self {
+            pos,
+            last_pos: pos,
+            velocity: Vec2::ZERO,
+            on_ground: true,
+            friction: 1.0,
+            through_player: false,
+            id: None,
+        }
+    }
+    pub fn update(
+        &mut self,
+        physics: &PhysicsConfig,
+        delta_time: f32,
+        tiles: &Tiles,
+        others: &[Rect],
+    ) {
+        self.last_pos = self.pos;
+        self.velocity.y += physics.gravity * delta_time;
+        self.velocity.x -= self.velocity.x * physics.ground_friction * self.friction * delta_time;
 
         if self.velocity.x.abs() <= 2.0 {
             self.velocity.x = 0.0;
             self.pos = self.pos.round();
         }
-        (self.pos, self.on_ground) = update_physicsbody(
+        let contacts;
+        (self.pos, contacts) = update_physicsbody(
             self.pos,
             &mut self.velocity,
+            Self::HITBOX,
             delta_time,
-            collision_tiles,
-            one_way_tiles,
+            tiles,
+            others,
         );
+        self.on_ground = contacts.down;
+        self.friction = contacts.friction();
+        // roll down slopes, with the part of gravity along the slope
+        let slope = contacts.slope();
+        self.velocity.x += physics.gravity * slope / (1.0 + slope * slope) * delta_time;
     }
     pub fn within_reach(&self, player_pos: &Vec2, player_grounded: bool) -> bool {
         if !player_grounded {
@@ -212,83 +314,310 @@ impl Pumpkin {
             (self.pos.x - player_pos.x).powi(2) + (self.pos.y - player_pos.y).powi(2) * 1.5;
         weighted_dist <= PUMPKIN_PICKUP_DIST.powi(2)
     }
-    pub fn draw(&self, assets: &Assets, player_pos: &Vec2, player_grounded: bool) {
+    /// Draws the pumpkin `alpha` of the way from the last tick to the current one.
+    pub fn draw(&self, assets: &Assets, player_pos: &Vec2, player_grounded: bool, alpha: f32) {
+        let pos = self.last_pos.lerp(self.pos, alpha).floor();
         let tile_y = if self.within_reach(player_pos, player_grounded) {
             3.0
         } else {
             2.0
         };
-        assets.tileset.draw_sprite(
-            self.pos.floor().x + 4.0,
-            self.pos.floor().y + 4.0,
-            0.0,
-            tile_y,
-            None,
-        );
+        assets
+            .tileset
+            .draw_sprite(pos.x + 4.0, pos.y + 4.0, 0.0, tile_y, None);
     }
 }
 
 pub struct World {
-    pub collision: Vec<Chunk>,
-    pub one_way_collision: Vec<Chunk>,
-    pub details: Vec<Chunk>,
-    pub background: Vec<Chunk>,
-    pub interactable: Vec<Chunk>,
+    pub collision: Layer,
+    pub one_way_collision: Layer,
+    pub details: Layer,
+    pub background: Layer,
 
+    pub spawns: Vec<Spawn>,
+    pub exits: Vec<Exit>,
+    pub npcs: Vec<Npc>,
+    pub triggers: Vec<Trigger>,
     pub pumpkins: Vec<Pumpkin>,
+    pub tileset: Tileset,
+    /// Properties of tiles that have any, by gid.
+    pub properties: HashMap<i16, TileProperties>,
+    /// Animated tiles, whose chunks are drawn every frame instead of pre-rendered.
+    pub animated: Vec<AnimatedTile>,
+    /// Areas where tiles were changed since the last `take_changed`.
+    changed: Vec<Rect>,
+    /// Id the next object added to the map gets.
+    pub next_object_id: u32,
 
     pub x_min: i16,
     pub x_max: i16,
     pub y_min: i16,
     pub y_max: i16,
 }
-#[expect(dead_code)]
+/// One of the tile layers of a world.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WorldLayer {
+    Background,
+    Collision,
+    Details,
+    OneWay,
+}
+impl WorldLayer {
+    /// Every layer, in the order they're drawn.
+    pub const ALL: [WorldLayer; 4] = [
+        WorldLayer::Background,
+        WorldLayer::Collision,
+        WorldLayer::Details,
+        WorldLayer::OneWay,
+    ];
+    /// Name of the layer in Tiled.
+    pub fn tiled_name(self) -> &'static str {
+        match self {
+            WorldLayer::Background => "Background",
+            WorldLayer::Collision => "Collision",
+            WorldLayer::Details => "Detail",
+            WorldLayer::OneWay => "OneWayCollision",
+        }
+    }
+}
 impl World {
-    pub fn get_interactable_spawn(&self, tile_index: i16) -> Option<Vec2> {
-        for chunk in self.interactable.iter() {
-            for (i, tile) in chunk.tiles.iter().enumerate() {
-                if *tile == tile_index + 1 {
-                    return Some(Vec2::new(
-                        (i as i16 % 16 + chunk.x) as f32 * 8.0,
-                        (i as i16 / 16 + chunk.y) as f32 * 8.0,
-                    ));
-                }
-            }
+    pub fn layer(&self, layer: WorldLayer) -> &Layer {
+        match layer {
+            WorldLayer::Background => &self.background,
+            WorldLayer::Collision => &self.collision,
+            WorldLayer::Details => &self.details,
+            WorldLayer::OneWay => &self.one_way_collision,
         }
-        None
     }
-    pub fn set_collision_tile(&mut self, x: i16, y: i16, tile: i16) {
-        let cx = ((x as f32 / 16.0).floor() * 16.0) as i16;
-        let cy = ((y as f32 / 16.0).floor() * 16.0) as i16;
+    /// Changes the tile at `x`, `y` on `layer`, for things like breakable blocks or gates.
+    /// Takes effect in physics straight away, and is redrawn after `take_changed`.
+    pub fn set_tile(&mut self, layer: WorldLayer, x: i16, y: i16, tile: i16) {
+        let layer = match layer {
+            WorldLayer::Background => &mut self.background,
+            WorldLayer::Collision => &mut self.collision,
+            WorldLayer::Details => &mut self.details,
+            WorldLayer::OneWay => &mut self.one_way_collision,
+        };
+        let old = layer.tile_at(x, y);
+        if old == tile {
+            return;
+        }
+        layer.set_tile(x, y, tile);
+        let pos = vec2(x as f32, y as f32) * 8.0 + layer.offset;
+        self.changed.push(Rect::new(pos.x, pos.y, 8.0, 8.0));
 
-        let chunk = self
-            .collision
-            .iter_mut()
-            .find(|f| f.x == cx && f.y == cy)
-            .unwrap();
-        chunk.tiles[(x - chunk.x + (y - chunk.y) * 16) as usize] = tile;
+        let (cx, cy) = (x.div_euclid(16) * 16, y.div_euclid(16) * 16);
+        self.x_min = self.x_min.min(cx);
+        self.x_max = self.x_max.max(cx);
+        self.y_min = self.y_min.min(cy);
+        self.y_max = self.y_max.max(cy);
+        if self.is_animated(old) || self.is_animated(tile) {
+            self.animated = self.find_animated();
+        }
     }
 }
-impl Default for World {
-    fn default() -> Self {
-        let xml = include_str!("../assets/world/world.tmx");
-        let collision = get_layer(xml, "Collision");
-        let one_way_collision = get_layer(xml, "OneWayCollision");
-        let detail = get_layer(xml, "Detail");
-        let interactable = get_layer(xml, "Interactable");
-        let background = get_layer(xml, "Background");
+impl World {
+    pub fn npc(&self, name: &str) -> Option<&Npc> {
+        self.npcs.iter().find(|f| f.name == name)
+    }
+    /// Position of the spawn point called `name`, falling back to the first spawn point.
+    pub fn spawn_point(&self, name: Option<&str>) -> Vec2 {
+        name.and_then(|name| self.spawns.iter().find(|f| f.name == name))
+            .or(self.spawns.first())
+            .map(|f| f.pos)
+            .unwrap_or_default()
+    }
+    /// The exit overlapping `hitbox`, if any.
+    pub fn exit_at(&self, hitbox: Rect) -> Option<&Exit> {
+        self.exits.iter().find(|f| f.rect.overlaps(&hitbox))
+    }
+    /// Everything that bodies in the level collide with.
+    pub fn tiles(&self) -> Tiles<'_> {
+        Tiles {
+            collision: &self.collision,
+            one_way: &self.one_way_collision,
+            properties: &self.properties,
+        }
+    }
+    /// Moves every pumpkin, from the bottom up so that stacks settle in one go.
+    /// They collide with each other, and with `player`, the player's hitbox, unless they were
+    /// just let go of.
+    pub fn update_pumpkins(&mut self, physics: &PhysicsConfig, delta_time: f32, player: Rect) {
+        let mut order: Vec<usize> = (0..self.pumpkins.len()).collect();
+        order.sort_by(|a, b| self.pumpkins[*b].pos.y.total_cmp(&self.pumpkins[*a].pos.y));
+        for index in order {
+            let others: Vec<Rect> = self
+                .pumpkins
+                .iter()
+                .enumerate()
+                .filter(|(other, _)| *other != index)
+                .map(|(_, f)| Pumpkin::HITBOX.at(f.pos))
+                .chain((!self.pumpkins[index].through_player).then_some(player))
+                .collect();
+            let tiles = Tiles {
+                collision: &self.collision,
+                one_way: &self.one_way_collision,
+                properties: &self.properties,
+            };
+            self.pumpkins[index].update(physics, delta_time, &tiles, &others);
+        }
+    }
+    /// Areas where tiles were changed since this was last called, which need to be redrawn.
+    pub fn take_changed(&mut self) -> Vec<Rect> {
+        std::mem::take(&mut self.changed)
+    }
+    fn is_animated(&self, tile: i16) -> bool {
+        self.properties.get(&tile).is_some_and(|f| f.animated)
+    }
+    /// Whether any layer has tiles within `region`.
+    pub fn has_tiles_in(&self, region: Rect) -> bool {
+        WorldLayer::ALL.into_iter().any(|f| {
+            let layer = self.layer(f);
+            layer.visible && layer.chunks_in(region).next().is_some()
+        })
+    }
+    /// Whether any animated tile on `layer` has its top left corner within `region`.
+    pub fn has_animated_in(&self, layer: WorldLayer, region: Rect) -> bool {
+        self.animated
+            .iter()
+            .any(|f| f.layer == layer && region.contains(f.pos))
+    }
+    /// Draws every tile layer within `region`, with the animated tiles as they are at `time`, in
+    /// milliseconds.
+    pub fn draw_layers(&self, assets: &Assets, region: Rect, time: u32) {
+        for kind in WorldLayer::ALL {
+            self.draw_layer(assets, kind, region);
+            self.draw_animated(assets, kind, region, time);
+        }
+    }
+    /// Draws the tiles of `layer` within `region`, except for the animated ones.
+    pub fn draw_layer(&self, assets: &Assets, layer: WorldLayer, region: Rect) {
+        self.layer(layer)
+            .draw(assets, &self.tileset, region, |tile| {
+                !self.is_animated(tile)
+            });
+    }
+    /// Draws the animated tiles of `layer` within `region` as they are at `time`, in milliseconds.
+    pub fn draw_animated(&self, assets: &Assets, layer: WorldLayer, region: Rect, time: u32) {
+        // each tile is only in one region, so tiles overlapping several aren't drawn twice
+        let animated = self
+            .animated
+            .iter()
+            .filter(|f| f.layer == layer && region.contains(f.pos));
+        for tile in animated {
+            let id = (tile.gid - self.tileset.first_gid as i16) as u32;
+            let id = self.tileset.tile_at_time(id, time);
+            draw_tile(assets, &self.tileset, id, tile.pos, tile.color);
+        }
+    }
+    fn find_animated(&self) -> Vec<AnimatedTile> {
+        let mut animated = Vec::new();
+        for kind in WorldLayer::ALL {
+            let layer = self.layer(kind);
+            if !layer.visible {
+                continue;
+            }
+            let color = WHITE.with_alpha(layer.opacity);
+            for chunk in layer.chunks.values() {
+                for (index, gid) in chunk.tiles.iter().enumerate() {
+                    if *gid == 0 || !self.is_animated(*gid) {
+                        continue;
+                    }
+                    let tile = vec2((index % 16) as f32, (index / 16) as f32);
+                    animated.push(AnimatedTile {
+                        pos: (vec2(chunk.x as f32, chunk.y as f32) + tile) * 8.0 + layer.offset,
+                        gid: *gid,
+                        color,
+                        layer: kind,
+                    });
+                }
+            }
+        }
+        animated
+    }
+    /// Draws the tile `gid` with its top left corner at `pos`.
+    pub fn draw_tile(&self, assets: &Assets, gid: i16, pos: Vec2, color: Color) {
+        let id = (gid - self.tileset.first_gid as i16) as u32;
+        draw_tile(assets, &self.tileset, id, pos, color);
+    }
+    /// Writes the tiles, pumpkins and NPC positions of the world into `tmx`, the contents of the
+    /// `.tmx` file it was loaded from.
+    pub fn write_tmx(&self, tmx: &str) -> Result<String, TiledError> {
+        let layers = WorldLayer::ALL
+            .into_iter()
+            .map(|f| (f.tiled_name(), self.layer(f).to_tiled()))
+            .collect();
+        // pumpkins that aren't in the map yet are added as new objects
+        let mut next_id = self.next_object_id;
+        let pumpkins = self
+            .pumpkins
+            .iter()
+            .map(|f| {
+                let id = f.id.unwrap_or_else(|| {
+                    next_id += 1;
+                    next_id - 1
+                });
+                (id, Pumpkin::HITBOX.at(f.pos))
+            })
+            .collect();
+        update_map(
+            tmx,
+            &MapUpdate {
+                layers,
+                moved_objects: self.npcs.iter().map(|f| (f.id, f.pos)).collect(),
+                synced_objects: vec![("pumpkin", pumpkins)],
+            },
+        )
+    }
+    /// A new id for an object added to the map.
+    pub fn new_object_id(&mut self) -> u32 {
+        self.next_object_id += 1;
+        self.next_object_id - 1
+    }
+    /// Replaces everything loaded from the map with `new`, keeping the pumpkins where they are.
+    pub fn reload(&mut self, new: World) {
+        let pumpkins = std::mem::take(&mut self.pumpkins);
+        *self = World { pumpkins, ..new };
+    }
+    /// Loads a world from the contents of a `.tmx` file.
+    ///
+    /// External tilesets are read through `load_tileset`, which gets the `source` path from the map.
+    pub fn from_tmx(
+        tmx: &str,
+        load_tileset: impl FnOnce(&str) -> Option<String>,
+    ) -> Result<Self, TiledError> {
+        let map = parse_map(tmx)?;
+        let tileset = map
+            .tileset(load_tileset)?
+            .ok_or_else(|| TiledError::Map("map has no tileset".to_string()))?;
+
+        let layer = |layer: WorldLayer| {
+            map.layer(layer.tiled_name())
+                .map(Layer::from_tiled)
+                .transpose()
+                .map(Option::unwrap_or_default)
+        };
         let mut world = World {
-            collision: get_all_chunks(collision),
-            one_way_collision: get_all_chunks(one_way_collision),
-            details: get_all_chunks(detail),
-            background: get_all_chunks(background),
-            interactable: get_all_chunks(interactable),
+            collision: layer(WorldLayer::Collision)?,
+            one_way_collision: layer(WorldLayer::OneWay)?,
+            details: layer(WorldLayer::Details)?,
+            background: layer(WorldLayer::Background)?,
             x_min: 999,
             y_min: 999,
             y_max: -999,
             x_max: -999,
+            spawns: Vec::new(),
+            exits: Vec::new(),
+            npcs: Vec::new(),
+            triggers: Vec::new(),
             pumpkins: Vec::new(),
+            properties: tile_properties(&tileset),
+            animated: Vec::new(),
+            changed: Vec::new(),
+            next_object_id: map.next_object_id,
+            tileset,
         };
+        world.animated = world.find_animated();
 
         // define x y min and max
         for layer in [
@@ -296,9 +625,8 @@ impl Default for World {
             &world.one_way_collision,
             &world.details,
             &world.background,
-            &world.interactable,
         ] {
-            for chunk in layer {
+            for chunk in layer.chunks.values() {
                 if chunk.x < world.x_min {
                     world.x_min = chunk.x;
                 }
@@ -314,114 +642,594 @@ impl Default for World {
             }
         }
 
-        // populate pumpkins array
-        for chunk in &world.interactable {
-            for (index, tile) in chunk.tiles.iter().enumerate() {
-                let x = (index % 16) as i16 + chunk.x;
-                let y = (index / 16) as i16 + chunk.y;
-                if *tile == 64 + 1 {
-                    world.pumpkins.push(Pumpkin {
-                        pos: vec2((x * 8) as f32, (y * 8) as f32),
-                        velocity: Vec2::ZERO,
-                        on_ground: true,
+        for object in map.objects() {
+            match object.kind.as_str() {
+                "player_spawn" => world.spawns.push(Spawn {
+                    name: object.name.clone(),
+                    pos: object.pos,
+                }),
+                "exit" => {
+                    let Some(level) = object.properties.string("level") else {
+                        return Err(TiledError::Map(format!(
+                            "exit {} is missing a \"level\" property",
+                            object.id
+                        )));
+                    };
+                    world.exits.push(Exit {
+                        rect: object.rect(),
+                        level: level.to_string(),
+                        spawn: object.properties.string("spawn").map(str::to_string),
+                        door: object.properties.bool("door").unwrap_or(false),
                     });
                 }
+                "npc" => world.npcs.push(Npc {
+                    id: object.id,
+                    name: object.name.clone(),
+                    pos: object.pos,
+                    properties: object.properties.clone(),
+                }),
+                "pumpkin" => world.pumpkins.push(Pumpkin {
+                    id: Some(object.id),
+                    ..Pumpkin::new(object.pos)
+                }),
+                "trigger" => world.triggers.push(Trigger {
+                    name: object.name.clone(),
+                    rect: object.rect(),
+                    properties: object.properties.clone(),
+                }),
+                _ => {}
             }
         }
 
-        world
+        Ok(world)
+    }
+}
+/// A tile on one of the layers that's redrawn every frame.
+pub struct AnimatedTile {
+    pub pos: Vec2,
+    pub gid: i16,
+    /// Tint from the opacity of the layer.
+    pub color: Color,
+    /// Layer the tile is on, which it's drawn in between the others with.
+    pub layer: WorldLayer,
+}
+
+pub struct Spawn {
+    pub name: String,
+    pub pos: Vec2,
+}
+
+/// Moves the player to another level, either on touch or, for doors, when pressing up.
+pub struct Exit {
+    pub rect: Rect,
+    pub level: String,
+    /// Name of the spawn point in `level`. Uses the level's first spawn point if `None`.
+    pub spawn: Option<String>,
+    pub door: bool,
+}
+
+pub struct Npc {
+    /// Id of the object in the map.
+    pub id: u32,
+    pub name: String,
+    pub pos: Vec2,
+    pub properties: Properties,
+}
+
+/// An area placed in Tiled that reacts to the player entering it.
+#[expect(dead_code)]
+pub struct Trigger {
+    pub name: String,
+    pub rect: Rect,
+    pub properties: Properties,
+}
+
+/// How a tile behaves, set up in Tiled with tile properties.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TileProperties {
+    /// Overrides whether the tile is solid, which otherwise depends on its layer.
+    pub solid: Option<bool>,
+    /// Overrides whether the tile is a one way platform, which otherwise depends on its layer.
+    pub one_way: Option<bool>,
+    /// Sends the player back to where they entered the level.
+    pub hazard: bool,
+    /// Multiplier for the friction of bodies standing on the tile.
+    pub friction: f32,
+    /// How much of their speed bodies keep when landing on the tile.
+    pub bouncy: f32,
+    pub climbable: bool,
+    /// Drawn every frame, instead of once when the level is loaded. On by default for tiles with
+    /// an animation.
+    pub animated: bool,
+    /// Partial tiles, set up with `height_left` and `height_right` in pixels from the bottom.
+    pub shape: TileShape,
+}
+impl Default for TileProperties {
+    fn default() -> Self {
+        Self {
+            solid: None,
+            one_way: None,
+            hazard: false,
+            friction: 1.0,
+            bouncy: 0.0,
+            climbable: false,
+            animated: false,
+            shape: TileShape::FULL,
+        }
+    }
+}
+
+/// Properties of every tile in `tileset` that has any, by gid.
+fn tile_properties(tileset: &Tileset) -> HashMap<i16, TileProperties> {
+    tileset
+        .tiles
+        .iter()
+        .map(|(id, properties)| {
+            let default = TileProperties::default();
+            let full = TileShape::FULL;
+            let tile = TileProperties {
+                solid: properties.bool("solid"),
+                one_way: properties.bool("one_way"),
+                hazard: properties.bool("hazard").unwrap_or(default.hazard),
+                friction: properties
+                    .float("friction")
+                    .unwrap_or(default.friction)
+                    .max(0.0),
+                bouncy: properties
+                    .float("bouncy")
+                    .unwrap_or(default.bouncy)
+                    .clamp(0.0, 1.0),
+                climbable: properties.bool("climbable").unwrap_or(default.climbable),
+                animated: properties
+                    .bool("animated")
+                    .unwrap_or(tileset.animations.contains_key(id)),
+                shape: TileShape {
+                    left: properties
+                        .float("height_left")
+                        .map_or(full.left, |f| f.clamp(0.0, full.left)),
+                    right: properties
+                        .float("height_right")
+                        .map_or(full.right, |f| f.clamp(0.0, full.right)),
+                },
+            };
+            ((tileset.first_gid + id) as i16, tile)
+        })
+        .collect()
+}
+
+/// A tile layer split into 16x16 chunks.
+pub struct Layer {
+    /// Chunks by the position of their top left tile.
+    pub chunks: HashMap<(i16, i16), Chunk>,
+    pub opacity: f32,
+    pub visible: bool,
+    pub offset: Vec2,
+    /// Tiled's flip and rotation flags of the tiles that have any, by position. Tiles are drawn
+    /// without them, they're only kept so saving the map doesn't lose them.
+    pub flips: HashMap<(i16, i16), u32>,
+}
+impl Default for Layer {
+    fn default() -> Self {
+        Self {
+            chunks: HashMap::new(),
+            opacity: 1.0,
+            visible: true,
+            offset: Vec2::ZERO,
+            flips: HashMap::new(),
+        }
+    }
+}
+impl Layer {
+    /// Converts a Tiled layer, of any chunk size, into 16x16 chunks.
+    fn from_tiled(layer: &TileLayer) -> Result<Self, TiledError> {
+        let mut new = Self {
+            chunks: HashMap::new(),
+            opacity: layer.opacity,
+            visible: layer.visible,
+            offset: layer.offset,
+            flips: HashMap::new(),
+        };
+        for tiled_chunk in &layer.chunks {
+            for (index, tile) in tiled_chunk.tiles.iter().enumerate() {
+                let x = tiled_chunk.x as i64 + (index as u32 % tiled_chunk.width) as i64;
+                let y = tiled_chunk.y as i64 + (index as u32 / tiled_chunk.width) as i64;
+                let err = |reason| TiledError::Layer {
+                    layer: layer.name.clone(),
+                    chunk: Some((tiled_chunk.x, tiled_chunk.y)),
+                    reason,
+                };
+                let gid = tile & GID_MASK;
+                let gid = i16::try_from(gid).map_err(|_| {
+                    err(format!(
+                        "tile {gid} at ({x}, {y}) is above the highest supported gid"
+                    ))
+                })?;
+                let (Ok(x), Ok(y)) = (i16::try_from(x), i16::try_from(y)) else {
+                    return Err(err(format!("tile at ({x}, {y}) is too far out")));
+                };
+                new.chunk_mut(x, y).tiles[index_in_chunk(x, y)] = gid;
+                if tile & !GID_MASK != 0 {
+                    new.flips.insert((x, y), tile & !GID_MASK);
+                }
+            }
+        }
+        Ok(new)
+    }
+    /// Chunks in the order Tiled stores them, row by row.
+    fn to_tiled(&self) -> Vec<TileChunk> {
+        let mut chunks: Vec<&Chunk> = self.chunks.values().collect();
+        chunks.sort_by_key(|f| (f.y, f.x));
+        chunks
+            .into_iter()
+            .map(|chunk| TileChunk {
+                x: chunk.x as i32,
+                y: chunk.y as i32,
+                width: 16,
+                height: 16,
+                tiles: (0..16 * 16)
+                    .map(|index| {
+                        let pos = (chunk.x + index % 16, chunk.y + index / 16);
+                        let flips = self.flips.get(&pos).copied().unwrap_or_default();
+                        chunk.tiles[index as usize] as u32 | flips
+                    })
+                    .collect(),
+            })
+            .collect()
+    }
+    /// The chunk containing the tile at `x`, `y`, which is created if there isn't one yet.
+    fn chunk_mut(&mut self, x: i16, y: i16) -> &mut Chunk {
+        let (cx, cy) = (x.div_euclid(16) * 16, y.div_euclid(16) * 16);
+        self.chunks.entry((cx, cy)).or_insert_with(|| Chunk {
+            x: cx,
+            y: cy,
+            tiles: vec![0; 16 * 16],
+        })
+    }
+    /// The tile at `x`, `y`, or 0 if there's none.
+    pub fn tile_at(&self, x: i16, y: i16) -> i16 {
+        let key = (x.div_euclid(16) * 16, y.div_euclid(16) * 16);
+        self.chunks
+            .get(&key)
+            .map_or(0, |f| f.tiles[index_in_chunk(x, y)])
+    }
+    pub fn set_tile(&mut self, x: i16, y: i16, tile: i16) {
+        if tile == 0 && self.tile_at(x, y) == 0 {
+            return;
+        }
+        self.chunk_mut(x, y).tiles[index_in_chunk(x, y)] = tile;
+        self.flips.remove(&(x, y));
+    }
+    /// Chunks that are at least partly within `region`, not counting ones only touching its edge.
+    fn chunks_in(&self, region: Rect) -> impl Iterator<Item = &Chunk> {
+        let size = 16.0 * 8.0;
+        let min = ((region.point() - self.offset) / size).floor().as_ivec2();
+        let max = ((region.point() + region.size() - self.offset) / size)
+            .ceil()
+            .as_ivec2();
+        (min.y..max.y)
+            .flat_map(move |y| (min.x..max.x).map(move |x| (x as i16 * 16, y as i16 * 16)))
+            .filter_map(|key| self.chunks.get(&key))
+    }
+    /// Draws the tiles of chunks within `region` that `draw` returns true for.
+    pub fn draw(
+        &self,
+        assets: &Assets,
+        tileset: &Tileset,
+        region: Rect,
+        draw: impl Fn(i16) -> bool + Copy,
+    ) {
+        if !self.visible {
+            return;
+        }
+        let color = WHITE.with_alpha(self.opacity);
+        for chunk in self.chunks_in(region) {
+            chunk.draw(assets, tileset, self.offset, color, draw);
+        }
     }
 }
 
+/// Index in `Chunk::tiles` of the tile at `x`, `y`, in whichever chunk contains it.
+fn index_in_chunk(x: i16, y: i16) -> usize {
+    (x.rem_euclid(16) + y.rem_euclid(16) * 16) as usize
+}
+
 pub struct Chunk {
     pub x: i16,
     pub y: i16,
     pub tiles: Vec<i16>,
 }
 impl Chunk {
-    pub fn tile_at(&self, x: usize, y: usize) -> Option<i16> {
-        if x > 16 {
-            return None;
-        }
-        self.tiles.get(x + y * 16).cloned()
-    }
-    pub fn draw(&self, assets: &Assets) {
+    pub fn draw(
+        &self,
+        assets: &Assets,
+        tileset: &Tileset,
+        offset: Vec2,
+        color: Color,
+        draw: impl Fn(i16) -> bool,
+    ) {
         for (index, tile) in self.tiles.iter().enumerate() {
-            if *tile == 0 {
+            if *tile == 0 || !draw(*tile) {
                 continue;
             }
-            let tile = *tile - 1;
+            let id = (*tile - tileset.first_gid as i16) as u32;
             let x = index % 16;
             let y = index / 16;
-            assets.tileset.draw_tile(
+            let pos = vec2(
                 (self.x * 8) as f32 + (x * 8) as f32,
                 (self.y * 8) as f32 + (y * 8) as f32,
-                (tile % 32) as f32,
-                (tile / 32) as f32,
-                None,
             );
+            draw_tile(assets, tileset, id, pos + offset, color);
         }
     }
 }
 
-fn get_all_chunks(xml: &str) -> Vec<Chunk> {
-    let mut chunks = Vec::new();
-    let mut xml = xml.to_string();
-    while let Some((current, remains)) = xml.split_once("</chunk>") {
-        let new = parse_chunk(current);
-        chunks.push(new);
-        xml = remains.to_string();
-    }
-
-    chunks
-}
-
-fn get_layer<'a>(xml: &'a str, layer: &str) -> &'a str {
-    let split = format!(" name=\"{layer}");
-    xml.split_once(&split)
-        .unwrap()
-        .1
-        .split_once(">")
-        .unwrap()
-        .1
-        .split_once("</layer>")
-        .unwrap()
-        .0
-}
-
-fn parse_chunk(xml: &str) -> Chunk {
-    let (tag, data) = xml
-        .split_once("<chunk ")
-        .unwrap()
-        .1
-        .split_once(">")
-        .unwrap();
-
-    let x = tag
-        .split_once("x=\"")
-        .unwrap()
-        .1
-        .split_once("\"")
-        .unwrap()
-        .0
-        .parse()
-        .unwrap();
-    let y = tag
-        .split_once("y=\"")
-        .unwrap()
-        .1
-        .split_once("\"")
-        .unwrap()
-        .0
-        .parse()
-        .unwrap();
-
-    let mut split = data.split(',');
-
-    let mut chunk = vec![0; 16 * 16];
-    for item in &mut chunk {
-        let a = split.next().unwrap().trim();
-        *item = a.parse().unwrap()
-    }
-    Chunk { x, y, tiles: chunk }
+/// Draws tile `id` of `tileset` with its top left corner at `pos`.
+fn draw_tile(assets: &Assets, tileset: &Tileset, id: u32, pos: Vec2, color: Color) {
+    let columns = tileset.columns;
+    assets.tileset.draw_tile_ex(
+        pos.x,
+        pos.y,
+        (id % columns) as f32,
+        (id / columns) as f32,
+        color,
+        None,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pumpkins_within_reach() {
+        let pumpkin = Pumpkin::new(vec2(100.0, 50.0));
+        assert!(pumpkin.within_reach(&vec2(100.0, 50.0), true));
+        assert!(pumpkin.within_reach(&vec2(91.0, 50.0), true));
+        assert!(pumpkin.within_reach(&vec2(106.0, 46.0), true));
+        assert!(!pumpkin.within_reach(&vec2(90.0, 50.0), true));
+        // height counts for more than distance to the side
+        assert!(!pumpkin.within_reach(&vec2(100.0, 42.0), true));
+        // can't pick things up mid air
+        assert!(!pumpkin.within_reach(&vec2(100.0, 50.0), false));
+    }
+
+    #[test]
+    fn tile_properties_from_tileset() {
+        let tsx = r#"<tileset name="test" tilewidth="8" tileheight="8" tilecount="4" columns="2">
+            <tile id="1">
+                <properties>
+                    <property name="height_left" type="int" value="0"/>
+                    <property name="height_right" type="float" value="4"/>
+                </properties>
+            </tile>
+            <tile id="2">
+                <properties>
+                    <property name="one_way" type="bool" value="true"/>
+                    <property name="friction" type="float" value="0.1"/>
+                    <property name="bouncy" type="float" value="2"/>
+                </properties>
+            </tile>
+            <tile id="3">
+                <properties>
+                    <property name="hazard" type="bool" value="true"/>
+                    <property name="climbable" type="bool" value="true"/>
+                    <property name="animated" type="bool" value="true"/>
+                </properties>
+            </tile>
+        </tileset>"#;
+        let properties = tile_properties(&crate::tiled::parse_tileset(tsx, 10).unwrap());
+        let default = TileProperties::default();
+        assert_eq!(
+            properties,
+            HashMap::from([
+                (
+                    11,
+                    TileProperties {
+                        shape: TileShape {
+                            left: 0.0,
+                            right: 4.0
+                        },
+                        ..default
+                    }
+                ),
+                (
+                    12,
+                    TileProperties {
+                        one_way: Some(true),
+                        friction: 0.1,
+                        bouncy: 1.0,
+                        ..default
+                    }
+                ),
+                (
+                    13,
+                    TileProperties {
+                        hazard: true,
+                        climbable: true,
+                        animated: true,
+                        ..default
+                    }
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn animated_tiles() {
+        let tsx = r#"<tileset name="test" tilewidth="8" tileheight="8" tilecount="8" columns="4">
+            <tile id="1">
+                <animation>
+                    <frame tileid="4" duration="100"/>
+                    <frame tileid="5" duration="50"/>
+                </animation>
+            </tile>
+            <tile id="2">
+                <properties><property name="animated" type="bool" value="false"/></properties>
+                <animation><frame tileid="6" duration="100"/></animation>
+            </tile>
+        </tileset>"#;
+        let tileset = crate::tiled::parse_tileset(tsx, 1).unwrap();
+        assert_eq!(tileset.tile_at_time(1, 0), 4);
+        assert_eq!(tileset.tile_at_time(1, 120), 5);
+        assert_eq!(tileset.tile_at_time(1, 160), 4);
+        assert_eq!(tileset.tile_at_time(3, 120), 3);
+
+        let properties = tile_properties(&tileset);
+        assert!(properties[&2].animated);
+        // drawn once, like any other tile
+        assert!(!properties[&3].animated);
+    }
+
+    #[test]
+    fn animated_tiles_keep_their_layer() {
+        let tmx = r#"<map width="2" height="1" tilewidth="8" tileheight="8">
+            <tileset firstgid="1" name="test" tilewidth="8" tileheight="8" tilecount="4" columns="2">
+                <tile id="1"><animation><frame tileid="3" duration="100"/></animation></tile>
+            </tileset>
+            <layer id="1" name="Background"><data encoding="csv">0,2</data></layer>
+            <layer id="2" name="Collision"><data encoding="csv">1,1</data></layer>
+        </map>"#;
+        let world = World::from_tmx(tmx, |_| None).unwrap();
+        assert_eq!(world.animated.len(), 1);
+        assert_eq!(world.animated[0].pos, vec2(8.0, 0.0));
+        assert_eq!(world.animated[0].layer, WorldLayer::Background);
+        let region = Rect::new(0.0, 0.0, 16.0, 8.0);
+        assert!(world.has_animated_in(WorldLayer::Background, region));
+        assert!(!world.has_animated_in(WorldLayer::Collision, region));
+        assert!(!world.has_animated_in(WorldLayer::Background, Rect::new(0.0, 0.0, 8.0, 8.0)));
+    }
+
+    #[test]
+    fn layers_set_tiles_anywhere() {
+        let mut layer = Layer::default();
+        layer.set_tile(-1, -17, 5);
+        layer.set_tile(3, 4, 6);
+        assert_eq!(layer.tile_at(-1, -17), 5);
+        assert_eq!(layer.tile_at(3, 4), 6);
+        assert_eq!(layer.tile_at(4, 3), 0);
+        assert_eq!(layer.tile_at(100, 100), 0);
+        assert!(layer.chunks.contains_key(&(-16, -32)));
+        // clearing tiles doesn't make chunks for nothing
+        layer.set_tile(100, 100, 0);
+        assert_eq!(layer.chunks.len(), 2);
+
+        let region = Rect::new(-8.0, -8.0, 16.0, 16.0);
+        let chunks: Vec<_> = layer.chunks_in(region).map(|f| (f.x, f.y)).collect();
+        assert_eq!(chunks, [(0, 0)]);
+    }
+
+    #[test]
+    fn edits_are_written_back_into_map() {
+        let tmx = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" width="4" height="4" tilewidth="8" tileheight="8" infinite="1" nextobjectid="5">
+ <tileset firstgid="1" name="test" tilewidth="8" tileheight="8" tilecount="4" columns="2"/>
+ <layer id="1" name="Collision" width="4" height="4">
+  <data encoding="csv">
+   <chunk x="0" y="0" width="16" height="16">
+1,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,
+0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,
+0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,
+0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,
+0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,
+0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,
+0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,
+0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,
+0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,
+0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,
+0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,
+0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,
+0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,
+0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,
+0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,
+0,0,0,0,0,0,0,0,0,0,0,0,0,0,0,0
+</chunk>
+  </data>
+ </layer>
+ <objectgroup id="2" name="Objects" offsetx="2" offsety="0">
+  <object id="1" name="guy" type="npc" x="10" y="20"/>
+  <object id="2" type="pumpkin" x="30" y="40" width="8" height="8"/>
+  <object id="3" name="start" type="player_spawn" x="50" y="60"/>
+ </objectgroup>
+</map>
+"#;
+        let mut world = World::from_tmx(tmx, |_| None).unwrap();
+        world.set_tile(WorldLayer::Collision, 0, 0, 0);
+        world.set_tile(WorldLayer::Collision, -3, 20, 2);
+        world.npcs[0].pos = vec2(16.0, 8.0);
+        // the pumpkin from the map keeps its object, the new one gets the next id
+        world.pumpkins[0].pos = vec2(64.0, 32.0);
+        world.pumpkins.push(Pumpkin::new(vec2(72.0, 32.0)));
+
+        let written = world.write_tmx(tmx).unwrap();
+        assert!(
+            written
+                .contains(r#"<object id="2" type="pumpkin" x="62" y="32" width="8" height="8"/>"#)
+        );
+        assert!(written.contains(r#"<object id="5" type="pumpkin" x="70" y="32""#));
+        world.pumpkins.clear();
+        assert!(!world.write_tmx(tmx).unwrap().contains("pumpkin"));
+
+        let world = World::from_tmx(&written, |_| None).unwrap();
+        assert_eq!(world.collision.tile_at(0, 0), 0);
+        assert_eq!(world.collision.tile_at(-3, 20), 2);
+        assert_eq!(world.npcs[0].name, "guy");
+        assert_eq!(world.npcs[0].pos, vec2(16.0, 8.0));
+        let pumpkins: Vec<_> = world.pumpkins.iter().map(|f| f.pos).collect();
+        assert_eq!(pumpkins, [vec2(64.0, 32.0), vec2(72.0, 32.0)]);
+        // everything else in the map is left alone
+        assert_eq!(world.spawns[0].pos, vec2(52.0, 60.0));
+        assert!(written.contains(r#"<object id="3" name="start" type="player_spawn""#));
+        assert!(written.contains(r#"nextobjectid="6""#));
+        assert_eq!(world.next_object_id, 6);
+    }
+
+    #[test]
+    fn keeps_flipped_tiles() {
+        // the first tile flipped horizontally, then diagonally and vertically
+        let tmx = r#"<map width="3" height="1" tilewidth="8" tileheight="8" nextobjectid="1">
+            <tileset firstgid="1" name="test" tilewidth="8" tileheight="8" tilecount="4" columns="2"/>
+            <layer id="1" name="Collision"><data encoding="csv">2147483649,1610612737,1</data></layer>
+            <objectgroup id="2" name="Objects"/>
+        </map>"#;
+        let world = World::from_tmx(tmx, |_| None).unwrap();
+        assert_eq!(world.collision.tile_at(0, 0), 1);
+        assert_eq!(world.collision.tile_at(1, 0), 1);
+        assert!(world.tiles().solid(1, 0).is_some());
+
+        let written = world.write_tmx(tmx).unwrap();
+        assert!(written.contains("2147483649,1610612737,1"));
+        let mut world = World::from_tmx(&written, |_| None).unwrap();
+        assert_eq!(world.collision.flips[&(0, 0)], 0x8000_0000);
+        assert_eq!(world.collision.flips[&(1, 0)], 0x6000_0000);
+        assert_eq!(world.collision.flips.len(), 2);
+
+        // placing a tile replaces the flipped one entirely
+        world.set_tile(WorldLayer::Collision, 0, 0, 2);
+        assert!(!world.collision.flips.contains_key(&(0, 0)));
+    }
+
+    #[test]
+    fn rejects_tiles_layers_cant_hold() {
+        let tmx = r#"<map width="2" height="1" tilewidth="8" tileheight="8">
+            <tileset firstgid="1" name="test" tilewidth="8" tileheight="8" tilecount="4" columns="2"/>
+            <layer id="1" name="Collision"><data encoding="csv">1,40000</data></layer>
+        </map>"#;
+        let err = World::from_tmx(tmx, |_| None).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "layer \"Collision\", chunk (0, 0): tile 40000 at (1, 0) is above the highest supported gid"
+        );
+
+        let tmx = r#"<map width="2" height="1" tilewidth="8" tileheight="8" infinite="1">
+            <tileset firstgid="1" name="test" tilewidth="8" tileheight="8" tilecount="4" columns="2"/>
+            <layer id="1" name="Collision"><data encoding="csv">
+                <chunk x="32767" y="0" width="2" height="1">1,1</chunk>
+            </data></layer>
+        </map>"#;
+        let err = World::from_tmx(tmx, |_| None).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "layer \"Collision\", chunk (32767, 0): tile at (32768, 0) is too far out"
+        );
+    }
 }
@@ -0,0 +1,208 @@
+//! Console commands for the game itself.
+//!
+//! Commands that change the game restart the replay being recorded, since it only has the input
+//! and couldn't reproduce the change.
+
+use macroquad::prelude::*;
+
+use crate::{
+    GameState, PumpkinEngine,
+    assets::Pumpkin,
+    console::{Command, Console},
+};
+
+pub fn register(console: &mut Console<PumpkinEngine>) {
+    console.register(Teleport);
+    console.register(SpawnPumpkin);
+    console.register(DeletePumpkin);
+    console.register(Set);
+    console.register(Level);
+    console.register(Noclip);
+    console.register(Win);
+}
+
+/// Parses `args` as a position in pixels, or `None` if there aren't any.
+fn parse_pos(args: &[&str]) -> Result<Option<Vec2>, String> {
+    match args {
+        [] => Ok(None),
+        [x, y] => {
+            let parse = |f: &str| f.parse().map_err(|_| format!("\"{f}\" isn't a number"));
+            Ok(Some(vec2(parse(x)?, parse(y)?)))
+        }
+        _ => Err("expected an x and y position".to_string()),
+    }
+}
+
+struct Teleport;
+impl Command<PumpkinEngine> for Teleport {
+    fn name(&self) -> &'static str {
+        "teleport"
+    }
+    fn usage(&self) -> &'static str {
+        "x y"
+    }
+    fn run(&self, engine: &mut PumpkinEngine, args: &[&str]) -> Result<String, String> {
+        let pos = parse_pos(args)?.ok_or("expected an x and y position")?;
+        engine.simulation.teleport(pos);
+        engine.restart_recording();
+        Ok(format!("teleported to {} {}", pos.x, pos.y))
+    }
+}
+
+struct SpawnPumpkin;
+impl Command<PumpkinEngine> for SpawnPumpkin {
+    fn name(&self) -> &'static str {
+        "spawn-pumpkin"
+    }
+    fn usage(&self) -> &'static str {
+        "(x y)"
+    }
+    fn run(&self, engine: &mut PumpkinEngine, args: &[&str]) -> Result<String, String> {
+        let simulation = &mut engine.simulation;
+        // above the player by default, so it lands on their head
+        let pos = parse_pos(args)?.unwrap_or(simulation.player.pos - vec2(0.0, 8.0));
+        let mut pumpkin = Pumpkin::new(pos);
+        pumpkin.on_ground = false;
+        simulation.world.pumpkins.push(pumpkin);
+        engine.restart_recording();
+        Ok(format!("spawned a pumpkin at {} {}", pos.x, pos.y))
+    }
+}
+
+struct DeletePumpkin;
+impl Command<PumpkinEngine> for DeletePumpkin {
+    fn name(&self) -> &'static str {
+        "delete-pumpkin"
+    }
+    fn usage(&self) -> &'static str {
+        "(all)"
+    }
+    fn run(&self, engine: &mut PumpkinEngine, args: &[&str]) -> Result<String, String> {
+        let simulation = &mut engine.simulation;
+        let pumpkins = &mut simulation.world.pumpkins;
+        if args == ["all"] {
+            let count = pumpkins.len();
+            pumpkins.clear();
+            engine.restart_recording();
+            return Ok(format!("deleted {count} pumpkins"));
+        }
+        let player = simulation.player.pos;
+        let nearest = (0..pumpkins.len())
+            .min_by(|a, b| {
+                let distance = |f: &usize| pumpkins[*f].pos.distance_squared(player);
+                distance(a).total_cmp(&distance(b))
+            })
+            .ok_or("there aren't any pumpkins")?;
+        let pumpkin = pumpkins.remove(nearest);
+        engine.restart_recording();
+        Ok(format!(
+            "deleted the pumpkin at {} {}",
+            pumpkin.pos.x, pumpkin.pos.y
+        ))
+    }
+    fn complete(&self, _engine: &PumpkinEngine, index: usize) -> Vec<String> {
+        match index {
+            0 => vec!["all".to_string()],
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Shows or changes a physics constant.
+struct Set;
+impl Command<PumpkinEngine> for Set {
+    fn name(&self) -> &'static str {
+        "set"
+    }
+    fn usage(&self) -> &'static str {
+        "constant (value)"
+    }
+    fn run(&self, engine: &mut PumpkinEngine, args: &[&str]) -> Result<String, String> {
+        let (name, value) = match args {
+            [name] => (*name, None),
+            [name, value] => (*name, Some(*value)),
+            _ => {
+                let names = engine.simulation.physics.names();
+                return Err(format!("constants: {}", names.join(" ")));
+            }
+        };
+        if let Some(value) = value {
+            let value = value
+                .parse()
+                .map_err(|_| format!("\"{value}\" isn't a number"))?;
+            engine.simulation.physics.set(name, value)?;
+            // kept for games started later too
+            engine.physics = engine.simulation.physics;
+            engine.restart_recording();
+        }
+        let value = engine
+            .simulation
+            .physics
+            .get(name)
+            .ok_or_else(|| format!("unknown constant \"{name}\""))?;
+        Ok(format!("{name} is {value}"))
+    }
+    fn complete(&self, engine: &PumpkinEngine, index: usize) -> Vec<String> {
+        match index {
+            0 => engine.simulation.physics.names(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+struct Level;
+impl Command<PumpkinEngine> for Level {
+    fn name(&self) -> &'static str {
+        "level"
+    }
+    fn usage(&self) -> &'static str {
+        "name (spawn)"
+    }
+    fn run(&self, engine: &mut PumpkinEngine, args: &[&str]) -> Result<String, String> {
+        let (level, spawn) = match args {
+            [level] => (*level, None),
+            [level, spawn] => (*level, Some(*spawn)),
+            _ => return Err("expected a level name".to_string()),
+        };
+        engine
+            .change_level(level, spawn)
+            .map_err(|err| err.to_string())?;
+        engine.restart_recording();
+        Ok(format!("loaded {level}"))
+    }
+    fn complete(&self, engine: &PumpkinEngine, index: usize) -> Vec<String> {
+        match index {
+            0 => engine.levels.names().map(str::to_string).collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+struct Noclip;
+impl Command<PumpkinEngine> for Noclip {
+    fn name(&self) -> &'static str {
+        "noclip"
+    }
+    fn run(&self, engine: &mut PumpkinEngine, _args: &[&str]) -> Result<String, String> {
+        engine.input.noclip = !engine.input.noclip;
+        Ok(format!(
+            "noclip {}",
+            if engine.input.noclip { "on" } else { "off" }
+        ))
+    }
+}
+
+/// Skips to the pumpkin being delivered.
+struct Win;
+impl Command<PumpkinEngine> for Win {
+    fn name(&self) -> &'static str {
+        "win"
+    }
+    fn run(&self, engine: &mut PumpkinEngine, _args: &[&str]) -> Result<String, String> {
+        engine.simulation.player.carrying = None;
+        engine.simulation.delivered = true;
+        engine.restart_recording();
+        engine.state = GameState::Cutscene { time: 0.0 };
+        Ok("pumpkin delivered".to_string())
+    }
+}
@@ -0,0 +1,334 @@
+//! Drop-down developer console, for poking at the game while it runs. Only in debug builds.
+//!
+//! Commands are registered by whatever they control, so the console itself knows nothing about
+//! the game.
+
+use macroquad::prelude::*;
+
+use crate::{assets::Assets, utils::SCREEN_WIDTH};
+
+/// Lines of output kept around.
+const MAX_OUTPUT: usize = 100;
+/// Lines of output shown above the prompt.
+const SHOWN_OUTPUT: usize = 8;
+const LINE_HEIGHT: f32 = 5.0;
+
+/// Something that can be typed into the console, which acts on `T`.
+pub trait Command<T> {
+    /// What the command is typed as.
+    fn name(&self) -> &'static str;
+    /// Arguments the command takes, shown by `help`.
+    fn usage(&self) -> &'static str {
+        ""
+    }
+    /// Runs the command, returning what to print.
+    fn run(&self, target: &mut T, args: &[&str]) -> Result<String, String>;
+    /// Values argument `index` could have, for tab completion.
+    fn complete(&self, _target: &T, _index: usize) -> Vec<String> {
+        Vec::new()
+    }
+}
+
+pub struct Console<T> {
+    pub open: bool,
+    commands: Vec<Box<dyn Command<T>>>,
+    /// What's being typed.
+    line: String,
+    /// Lines that were run, oldest first.
+    history: Vec<String>,
+    /// Position in `history` while going through it with up and down.
+    browsing: Option<usize>,
+    /// Lines printed, oldest first.
+    output: Vec<String>,
+}
+
+impl<T> Default for Console<T> {
+    fn default() -> Self {
+        Self {
+            open: false,
+            commands: Vec::new(),
+            line: String::new(),
+            history: Vec::new(),
+            browsing: None,
+            output: Vec::new(),
+        }
+    }
+}
+
+impl<T> Console<T> {
+    pub fn register(&mut self, command: impl Command<T> + 'static) {
+        self.commands.push(Box::new(command));
+    }
+    /// Opens the console, or closes it if it's open.
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        // forget whatever was typed while the console was closed
+        while get_char_pressed().is_some() {}
+    }
+    /// Handles typing, while the console is open.
+    pub fn update(&mut self, target: &mut T) {
+        while let Some(char) = get_char_pressed() {
+            if !char.is_control() && char != '`' {
+                self.line.push(char.to_ascii_lowercase());
+            }
+        }
+        if is_key_pressed(KeyCode::Backspace) {
+            self.line.pop();
+        }
+        if is_key_pressed(KeyCode::Tab) {
+            self.complete(target);
+        } else if is_key_pressed(KeyCode::Up) {
+            self.previous();
+        } else if is_key_pressed(KeyCode::Down) {
+            self.next();
+        } else if is_key_pressed(KeyCode::Enter) {
+            let line = std::mem::take(&mut self.line);
+            self.run(target, &line);
+        } else if is_key_pressed(KeyCode::Escape) {
+            self.open = false;
+        }
+    }
+    fn print(&mut self, text: &str) {
+        self.output.extend(text.lines().map(str::to_string));
+        let excess = self.output.len().saturating_sub(MAX_OUTPUT);
+        self.output.drain(..excess);
+    }
+    fn find(&self, name: &str) -> Option<&dyn Command<T>> {
+        self.commands
+            .iter()
+            .find(|f| f.name() == name)
+            .map(|f| f.as_ref())
+    }
+    /// Runs `line`, printing it and what it results in.
+    pub fn run(&mut self, target: &mut T, line: &str) {
+        self.browsing = None;
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let Some((&name, args)) = words.split_first() else {
+            return;
+        };
+        if self.history.last().is_none_or(|f| f != line) {
+            self.history.push(line.to_string());
+        }
+        self.print(&format!("+ {line}"));
+
+        let result = if name == "help" {
+            let lines: Vec<String> = self
+                .commands
+                .iter()
+                .map(|f| format!("{} {}", f.name(), f.usage()))
+                .collect();
+            Ok(lines.join("\n"))
+        } else {
+            match self.find(name) {
+                Some(command) => command.run(target, args),
+                None => Err(format!("unknown command \"{name}\", try help")),
+            }
+        };
+        match result {
+            Ok(text) | Err(text) => self.print(&text),
+        }
+    }
+    /// Completes the word being typed, or as much of it as all the possibilities share,
+    /// printing them if there are several.
+    pub fn complete(&mut self, target: &T) {
+        let mut words: Vec<&str> = self.line.split_whitespace().collect();
+        let typing = if self.line.ends_with(' ') || self.line.is_empty() {
+            ""
+        } else {
+            words.pop().unwrap_or_default()
+        };
+        let options = match words.split_first() {
+            None => {
+                let names = self.commands.iter().map(|f| f.name().to_string());
+                names.chain(["help".to_string()]).collect()
+            }
+            Some((name, args)) => self
+                .find(name)
+                .map(|f| f.complete(target, args.len()))
+                .unwrap_or_default(),
+        };
+        let matching: Vec<String> = options
+            .into_iter()
+            .filter(|f| f.starts_with(typing))
+            .collect();
+        let Some(first) = matching.first() else {
+            return;
+        };
+
+        let mut line = words.join(" ");
+        if !line.is_empty() {
+            line.push(' ');
+        }
+        if let [only] = matching.as_slice() {
+            line += only;
+            line.push(' ');
+        } else {
+            let shared = matching.iter().fold(first.len(), |shared, option| {
+                let same = first.chars().zip(option.chars());
+                same.take_while(|(a, b)| a == b).count().min(shared)
+            });
+            line += &first[..shared];
+            self.print(&matching.join("  "));
+        }
+        self.line = line;
+    }
+    /// Goes back one line in the history.
+    fn previous(&mut self) {
+        let index = match self.browsing {
+            Some(index) => index.saturating_sub(1),
+            None => match self.history.len().checked_sub(1) {
+                Some(index) => index,
+                None => return,
+            },
+        };
+        self.browsing = Some(index);
+        self.line = self.history[index].clone();
+    }
+    /// Goes forward one line in the history, to an empty line after the last one.
+    fn next(&mut self) {
+        let Some(index) = self.browsing else {
+            return;
+        };
+        if index + 1 < self.history.len() {
+            self.browsing = Some(index + 1);
+            self.line = self.history[index + 1].clone();
+        } else {
+            self.browsing = None;
+            self.line.clear();
+        }
+    }
+    /// Draws the console across the top of the screen. Has to be drawn without a camera offset,
+    /// with `(0, 0)` at the top left.
+    pub fn draw(&self, assets: &Assets) {
+        let shown = &self.output[self.output.len().saturating_sub(SHOWN_OUTPUT)..];
+        let height = (SHOWN_OUTPUT + 1) as f32 * LINE_HEIGHT + 4.0;
+        draw_rectangle(0.0, 0.0, SCREEN_WIDTH, height, Color::from_hex(0x3b1725));
+        draw_rectangle(
+            0.0,
+            0.0,
+            SCREEN_WIDTH,
+            height - 1.0,
+            Color::from_hex(0xfffc40),
+        );
+        let top = (SHOWN_OUTPUT - shown.len()) as f32 * LINE_HEIGHT + 2.0;
+        assets.draw_text(&shown.join("\n"), 2.0, top);
+
+        let prompt_y = SHOWN_OUTPUT as f32 * LINE_HEIGHT + 2.0;
+        let (width, _) = assets.draw_text(&format!("+ {}", self.line), 2.0, prompt_y);
+        draw_rectangle(
+            2.0 + width + 1.0,
+            prompt_y + 1.0,
+            3.0,
+            4.0,
+            Color::from_hex(0x3b1725),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Add;
+    impl Command<i32> for Add {
+        fn name(&self) -> &'static str {
+            "add"
+        }
+        fn run(&self, target: &mut i32, args: &[&str]) -> Result<String, String> {
+            let amount: i32 = args
+                .first()
+                .and_then(|f| f.parse().ok())
+                .ok_or("usage: add <amount>")?;
+            *target += amount;
+            Ok(target.to_string())
+        }
+        fn complete(&self, _target: &i32, index: usize) -> Vec<String> {
+            match index {
+                0 => vec!["10".to_string(), "100".to_string(), "200".to_string()],
+                _ => Vec::new(),
+            }
+        }
+    }
+
+    struct Subtract;
+    impl Command<i32> for Subtract {
+        fn name(&self) -> &'static str {
+            "subtract"
+        }
+        fn run(&self, target: &mut i32, _args: &[&str]) -> Result<String, String> {
+            *target -= 1;
+            Ok(target.to_string())
+        }
+    }
+
+    fn console() -> Console<i32> {
+        let mut console = Console::default();
+        console.register(Add);
+        console.register(Subtract);
+        console
+    }
+
+    #[test]
+    fn runs_commands() {
+        let mut console = console();
+        let mut total = 0;
+        console.run(&mut total, "add 5");
+        console.run(&mut total, "  subtract ");
+        console.run(&mut total, "add");
+        console.run(&mut total, "multiply 2");
+        assert_eq!(total, 4);
+        assert_eq!(
+            console.output,
+            [
+                "+ add 5",
+                "5",
+                "+   subtract ",
+                "4",
+                "+ add",
+                "usage: add <amount>",
+                "+ multiply 2",
+                "unknown command \"multiply\", try help"
+            ]
+        );
+    }
+
+    #[test]
+    fn goes_through_history() {
+        let mut console = console();
+        let mut total = 0;
+        console.run(&mut total, "add 1");
+        console.run(&mut total, "add 2");
+        // repeats are only remembered once
+        console.run(&mut total, "add 2");
+        console.previous();
+        assert_eq!(console.line, "add 2");
+        console.previous();
+        console.previous();
+        assert_eq!(console.line, "add 1");
+        console.next();
+        assert_eq!(console.line, "add 2");
+        console.next();
+        assert_eq!(console.line, "");
+    }
+
+    #[test]
+    fn completes_names_and_arguments() {
+        let mut console = console();
+        console.line = "su".to_string();
+        console.complete(&0);
+        assert_eq!(console.line, "subtract ");
+
+        console.line = "add 1".to_string();
+        console.complete(&0);
+        assert_eq!(console.line, "add 10");
+        assert_eq!(console.output, ["10  100"]);
+
+        console.line = "add ".to_string();
+        console.complete(&0);
+        assert_eq!(console.line, "add ");
+
+        console.line = "h".to_string();
+        console.complete(&0);
+        assert_eq!(console.line, "help ");
+    }
+}
@@ -0,0 +1,237 @@
+//! Debug overlay and free camera, for seeing what the physics is doing. Only in debug builds.
+
+use std::collections::VecDeque;
+
+use macroquad::prelude::*;
+
+use crate::{
+    assets::{Assets, Pumpkin, World},
+    physics::{Hitbox, TileShape},
+    player::Player,
+    render::CHUNK_SIZE,
+    utils::*,
+};
+
+/// How many frames the frame time graph covers.
+const GRAPH_FRAMES: usize = 64;
+/// Frame time at the top of the graph, in seconds.
+const GRAPH_MAX: f32 = 1.0 / 20.0;
+const GRAPH_HEIGHT: f32 = 20.0;
+/// How many seconds of movement the velocity lines show.
+const VELOCITY_SCALE: f32 = 0.1;
+const MIN_ZOOM: f32 = 1.0 / 8.0;
+const MAX_ZOOM: f32 = 4.0;
+
+const SOLID_COLOR: Color = Color::new(1.0, 0.2, 0.2, 0.8);
+const ONE_WAY_COLOR: Color = Color::new(0.3, 0.5, 1.0, 0.8);
+const CHUNK_COLOR: Color = Color::new(1.0, 1.0, 1.0, 0.3);
+const GROUNDED_COLOR: Color = GREEN;
+const AIRBORNE_COLOR: Color = ORANGE;
+
+#[derive(Default)]
+pub struct DebugOverlay {
+    pub enabled: bool,
+    /// Camera detached from the player, if it is.
+    pub free_camera: Option<FreeCamera>,
+    /// Lengths of the last frames, in seconds, newest last.
+    frame_times: VecDeque<f32>,
+}
+
+pub struct FreeCamera {
+    /// Where the camera is centered, in pixels.
+    pub pos: Vec2,
+    /// How much bigger things are drawn than normal.
+    pub zoom: f32,
+    /// Mouse position on screen last frame, for dragging the camera around.
+    last_mouse: Vec2,
+}
+
+impl DebugOverlay {
+    /// Handles the keys and mouse. The camera is at `camera` if it isn't detached already, and the
+    /// game is drawn `scale` times as big on screen.
+    pub fn update(&mut self, camera: Vec2, scale: f32) {
+        self.frame_times.push_back(get_frame_time());
+        if self.frame_times.len() > GRAPH_FRAMES {
+            self.frame_times.pop_front();
+        }
+        if !cfg!(debug_assertions) {
+            return;
+        }
+
+        if is_key_pressed(KeyCode::F3) {
+            self.enabled = !self.enabled;
+        }
+        if is_key_pressed(KeyCode::F4) {
+            self.free_camera = match self.free_camera {
+                Some(_) => None,
+                None => Some(FreeCamera {
+                    pos: camera,
+                    zoom: 1.0,
+                    last_mouse: mouse_position().into(),
+                }),
+            };
+        }
+        if let Some(camera) = &mut self.free_camera {
+            camera.update(scale);
+        }
+    }
+    /// Smoothed frames per second and frame time in milliseconds, over the last frames.
+    fn frame_stats(&self) -> (f32, f32) {
+        let total: f32 = self.frame_times.iter().sum();
+        if total <= 0.0 {
+            return (0.0, 0.0);
+        }
+        let average = total / self.frame_times.len() as f32;
+        (1.0 / average, average * 1000.0)
+    }
+    /// Draws tile outlines, hitboxes and chunk boundaries within `view`, over the world.
+    pub fn draw_world(&self, world: &World, player: &Player, view: Rect, alpha: f32) {
+        let line = 1.0;
+
+        // chunk boundaries
+        let start = (view.point() / CHUNK_SIZE).floor() * CHUNK_SIZE;
+        let mut x = start.x;
+        while x < view.right() {
+            draw_line(x, view.y, x, view.bottom(), line, CHUNK_COLOR);
+            x += CHUNK_SIZE;
+        }
+        let mut y = start.y;
+        while y < view.bottom() {
+            draw_line(view.x, y, view.right(), y, line, CHUNK_COLOR);
+            y += CHUNK_SIZE;
+        }
+
+        // collision, with the shape each tile actually collides with
+        let tiles = world.tiles();
+        let min = (view.point() / 8.0).floor().as_ivec2();
+        let max = ((view.point() + view.size()) / 8.0).ceil().as_ivec2();
+        for y in min.y..max.y {
+            for x in min.x..max.x {
+                let (x, y) = (x as i16, y as i16);
+                let pos = vec2(x as f32, y as f32) * 8.0;
+                if let Some(tile) = tiles.solid(x, y) {
+                    draw_tile_shape(pos, tile.shape, true, SOLID_COLOR);
+                } else if let Some(tile) = tiles.one_way(x, y) {
+                    draw_tile_shape(pos, tile.shape, false, ONE_WAY_COLOR);
+                }
+            }
+        }
+
+        for pumpkin in &world.pumpkins {
+            let pos = pumpkin.last_pos.lerp(pumpkin.pos, alpha);
+            draw_body(Pumpkin::HITBOX, pos, pumpkin.velocity, pumpkin.on_ground);
+        }
+        let pos = player.interpolated_pos(alpha);
+        draw_body(Player::HITBOX, pos, player.velocity, player.on_ground);
+        // pumpkins with their top left corner in here can be picked up
+        let reach = PUMPKIN_PICKUP_DIST;
+        draw_ellipse_lines(
+            pos.x,
+            pos.y,
+            reach,
+            reach / 1.5_f32.sqrt(),
+            0.0,
+            line,
+            YELLOW,
+        );
+    }
+    /// Draws the frame rate, the frame time graph and what the player is doing, in the top left
+    /// of the screen. Has to be drawn without a camera offset, with `(0, 0)` at the top left.
+    pub fn draw_hud(&self, assets: &Assets, player: &Player) {
+        let (fps, frame_time) = self.frame_stats();
+        let state = if player.climbing {
+            "climbing"
+        } else if player.on_ground {
+            "on ground"
+        } else {
+            "in air"
+        };
+        let zoom = self.free_camera.as_ref().map_or(1.0, |f| f.zoom);
+        let text = format!(
+            "{fps:.0} fps {frame_time:.1}ms\nx {:.1} y {:.1}\nvx {:.1} vy {:.1}\n{state}\nzoom {zoom}",
+            player.pos.x, player.pos.y, player.velocity.x, player.velocity.y,
+        );
+        let size = text_box_size(&text);
+        draw_text_box(&text, assets, vec2(2.0, 2.0) + size / 2.0);
+
+        let top = size.y + 4.0;
+        let width = GRAPH_FRAMES as f32;
+        draw_rectangle(2.0, top, width, GRAPH_HEIGHT, BLACK.with_alpha(0.5));
+        for (index, time) in self.frame_times.iter().enumerate() {
+            let height = (time / GRAPH_MAX).min(1.0) * GRAPH_HEIGHT;
+            let color = if *time > TICK * 1.5 { RED } else { GREEN };
+            let x = 2.0 + index as f32;
+            draw_line(
+                x + 0.5,
+                top + GRAPH_HEIGHT,
+                x + 0.5,
+                top + GRAPH_HEIGHT - height,
+                1.0,
+                color,
+            );
+        }
+        // a tick, which frames should take at most
+        let tick = top + GRAPH_HEIGHT - TICK / GRAPH_MAX * GRAPH_HEIGHT;
+        draw_line(2.0, tick, 2.0 + width, tick, 1.0, WHITE.with_alpha(0.5));
+    }
+}
+
+impl FreeCamera {
+    /// Drags the camera with the right mouse button, and zooms with the scroll wheel or -/=.
+    fn update(&mut self, scale: f32) {
+        let mouse = Vec2::from(mouse_position());
+        if is_mouse_button_down(MouseButton::Right) {
+            self.pos -= (mouse - self.last_mouse) / scale / self.zoom;
+        }
+        self.last_mouse = mouse;
+
+        let (_, wheel) = mouse_wheel();
+        if wheel > 0.0 || is_key_pressed(KeyCode::Equal) {
+            self.zoom = (self.zoom * 2.0).min(MAX_ZOOM);
+        } else if wheel < 0.0 || is_key_pressed(KeyCode::Minus) {
+            self.zoom = (self.zoom / 2.0).max(MIN_ZOOM);
+        }
+    }
+}
+
+/// Outlines a tile at `pos` with `shape`. Only the top of one way tiles is drawn.
+fn draw_tile_shape(pos: Vec2, shape: TileShape, solid: bool, color: Color) {
+    let bottom = pos.y + 8.0;
+    let left = vec2(pos.x, bottom - shape.left);
+    let right = vec2(pos.x + 8.0, bottom - shape.right);
+    draw_line(left.x, left.y, right.x, right.y, 1.0, color);
+    if solid {
+        draw_line(left.x, left.y, left.x, bottom, 1.0, color);
+        draw_line(right.x, right.y, right.x, bottom, 1.0, color);
+        draw_line(pos.x, bottom, pos.x + 8.0, bottom, 1.0, color);
+    }
+}
+
+/// Outlines a body's hitbox, colored by whether it's on the ground, and a line showing its velocity.
+fn draw_body(hitbox: Hitbox, pos: Vec2, velocity: Vec2, on_ground: bool) {
+    let rect = hitbox.at(pos);
+    let color = if on_ground {
+        GROUNDED_COLOR
+    } else {
+        AIRBORNE_COLOR
+    };
+    draw_rectangle_lines(rect.x, rect.y, rect.w, rect.h, 1.0, color);
+    let center = rect.center();
+    let end = center + velocity * VELOCITY_SCALE;
+    draw_line(center.x, center.y, end.x, end.y, 1.0, SKYBLUE);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn averages_frame_times() {
+        let mut overlay = DebugOverlay::default();
+        assert_eq!(overlay.frame_stats(), (0.0, 0.0));
+        overlay.frame_times.extend([0.01, 0.03]);
+        let (fps, frame_time) = overlay.frame_stats();
+        assert!((fps - 50.0).abs() < 0.01);
+        assert!((frame_time - 20.0).abs() < 0.01);
+    }
+}
@@ -0,0 +1,225 @@
+//! Level editor for debug builds, for moving things around without a round trip through Tiled.
+//!
+//! Edits a copy of the level as it is in its map, which is what gets saved, and makes the same
+//! edits to the world being played so they can be tried out straight away.
+
+use macroquad::prelude::*;
+
+use crate::{assets::*, utils::*};
+
+/// How fast the camera pans, in pixels per second.
+const PAN_SPEED: f32 = 240.0;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Tool {
+    Tiles,
+    Pumpkins,
+    Npcs,
+}
+
+pub struct Editor {
+    /// Where the camera is centered, in pixels.
+    pub camera: Vec2,
+    tool: Tool,
+    layer: WorldLayer,
+    /// Gid painted with the tile tool.
+    tile: i16,
+    /// Index of the NPC being dragged.
+    dragging: Option<usize>,
+    /// Name of the level being edited, and its world as loaded from the map with only the edits
+    /// made here. Things in the world being played have moved on from the map, so aren't saved.
+    map: Option<(String, World)>,
+    /// Whether the map has edits that haven't been saved yet.
+    unsaved: bool,
+}
+impl Default for Editor {
+    fn default() -> Self {
+        Self {
+            camera: Vec2::ZERO,
+            tool: Tool::Tiles,
+            layer: WorldLayer::Collision,
+            tile: 1,
+            dragging: None,
+            map: None,
+            unsaved: false,
+        }
+    }
+}
+impl Editor {
+    /// Starts editing `level`, whose map was loaded as `map`.
+    pub fn open(&mut self, level: &str, map: World) {
+        self.map = Some((level.to_string(), map));
+        self.dragging = None;
+        self.unsaved = false;
+    }
+    /// Whether `level` is being edited, with edits that haven't been saved yet.
+    pub fn unsaved(&self, level: &str) -> bool {
+        self.unsaved && self.map(level).is_some()
+    }
+    /// Marks the edits made so far as saved.
+    pub fn saved(&mut self) {
+        self.unsaved = false;
+    }
+    /// Areas of the map that were edited since this was last called, which need to be redrawn.
+    pub fn take_changed(&mut self) -> Vec<Rect> {
+        self.map
+            .as_mut()
+            .map(|(_, map)| map.take_changed())
+            .unwrap_or_default()
+    }
+    /// The map of `level` with the edits made so far, if it's the level being edited.
+    pub fn map(&self, level: &str) -> Option<&World> {
+        self.map
+            .as_ref()
+            .filter(|(name, _)| name == level)
+            .map(|(_, map)| map)
+    }
+    /// Handles the keyboard and mouse, which is at `mouse` in the world. Edits are made to the map
+    /// of `level` and to `world`, the same level being played.
+    pub fn update(&mut self, level: &str, world: &mut World, mouse: Vec2, delta_time: f32) {
+        let mut pan = Vec2::ZERO;
+        for (key, direction) in [
+            (KeyCode::Left, vec2(-1.0, 0.0)),
+            (KeyCode::Right, vec2(1.0, 0.0)),
+            (KeyCode::Up, vec2(0.0, -1.0)),
+            (KeyCode::Down, vec2(0.0, 1.0)),
+        ] {
+            if is_key_down(key) {
+                pan += direction;
+            }
+        }
+        self.camera += pan * PAN_SPEED * delta_time;
+
+        let keys = [KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4];
+        for (key, layer) in keys.into_iter().zip(WorldLayer::ALL) {
+            if is_key_pressed(key) {
+                self.tool = Tool::Tiles;
+                self.layer = layer;
+            }
+        }
+        if is_key_pressed(KeyCode::P) {
+            self.tool = Tool::Pumpkins;
+        } else if is_key_pressed(KeyCode::N) {
+            self.tool = Tool::Npcs;
+        }
+
+        let (_, wheel) = mouse_wheel();
+        let scroll = if wheel > 0.0 || is_key_pressed(KeyCode::E) {
+            1
+        } else if wheel < 0.0 || is_key_pressed(KeyCode::Q) {
+            -1
+        } else {
+            0
+        };
+        let Some((_, map)) = self.map.as_mut().filter(|(name, _)| name == level) else {
+            return;
+        };
+        let first = world.tileset.first_gid as i16;
+        let last = first + world.tileset.tile_count as i16 - 1;
+        self.tile = (self.tile + scroll).clamp(first, last);
+
+        let tile = (mouse / 8.0).floor();
+        let (x, y) = (tile.x as i16, tile.y as i16);
+        let snapped = tile * 8.0;
+        match self.tool {
+            Tool::Tiles => {
+                let mut set_tile = |tile| {
+                    self.unsaved |= map.layer(self.layer).tile_at(x, y) != tile;
+                    map.set_tile(self.layer, x, y, tile);
+                    world.set_tile(self.layer, x, y, tile);
+                };
+                if is_mouse_button_down(MouseButton::Left) {
+                    set_tile(self.tile);
+                } else if is_mouse_button_down(MouseButton::Right) {
+                    set_tile(0);
+                } else if is_mouse_button_pressed(MouseButton::Middle) {
+                    // pick the tile under the mouse
+                    let picked = map.layer(self.layer).tile_at(x, y);
+                    if picked != 0 {
+                        self.tile = picked;
+                    }
+                }
+            }
+            Tool::Pumpkins => {
+                let under = map
+                    .pumpkins
+                    .iter()
+                    .position(|f| Pumpkin::HITBOX.at(f.pos).contains(mouse));
+                if is_mouse_button_pressed(MouseButton::Left) && under.is_none() {
+                    let id = Some(map.new_object_id());
+                    map.pumpkins.push(Pumpkin {
+                        id,
+                        ..Pumpkin::new(snapped)
+                    });
+                    world.pumpkins.push(Pumpkin {
+                        id,
+                        ..Pumpkin::new(snapped)
+                    });
+                    self.unsaved = true;
+                } else if is_mouse_button_pressed(MouseButton::Right)
+                    && let Some(index) = under
+                {
+                    let id = map.pumpkins.remove(index).id;
+                    world.pumpkins.retain(|f| f.id != id);
+                    self.unsaved = true;
+                }
+            }
+            Tool::Npcs => {
+                if is_mouse_button_pressed(MouseButton::Left) {
+                    self.dragging = map
+                        .npcs
+                        .iter()
+                        .position(|f| Rect::new(f.pos.x, f.pos.y, 8.0, 8.0).contains(mouse));
+                } else if !is_mouse_button_down(MouseButton::Left) {
+                    self.dragging = None;
+                }
+                if let Some(index) = self.dragging {
+                    let npc = &mut map.npcs[index];
+                    self.unsaved |= npc.pos != snapped;
+                    npc.pos = snapped;
+                    if let Some(playing) = world.npcs.iter_mut().find(|f| f.id == npc.id) {
+                        playing.pos = snapped;
+                    }
+                }
+            }
+        }
+    }
+    /// Draws the cursor at `mouse`, and what the controls are. `status` is shown under them.
+    /// `world` is the map being edited.
+    pub fn draw(&self, assets: &Assets, world: &World, mouse: Vec2, status: Option<&str>) {
+        let snapped = (mouse / 8.0).floor() * 8.0;
+        if self.tool == Tool::Tiles {
+            world.draw_tile(assets, self.tile, snapped, WHITE.with_alpha(0.6));
+        }
+        draw_rectangle_lines(
+            snapped.x,
+            snapped.y,
+            8.0,
+            8.0,
+            1.0,
+            Color::from_hex(0xfffc40),
+        );
+        for npc in &world.npcs {
+            draw_rectangle_lines(npc.pos.x, npc.pos.y, 8.0, 8.0, 1.0, WHITE);
+        }
+
+        let tool = match self.tool {
+            Tool::Tiles => format!("{} {}", self.layer.tiled_name().to_lowercase(), self.tile),
+            Tool::Pumpkins => "pumpkins".to_string(),
+            Tool::Npcs => "npcs".to_string(),
+        };
+        let mut text = format!(
+            "editor - {tool}\n1-4: layer  p: pumpkins  n: npcs\nq/e: tile  ctrl+s: save  f2: play"
+        );
+        if let Some(status) = status {
+            text += "\n";
+            text += status;
+        }
+        let top = self.camera.floor().y - SCREEN_HEIGHT / 2.0;
+        let center = vec2(
+            self.camera.floor().x,
+            top + text_box_size(&text).y / 2.0 + 2.0,
+        );
+        draw_text_box(&text, assets, center);
+    }
+}
@@ -0,0 +1,245 @@
+//! Controller input, from any number of connected controllers.
+//!
+//! Native builds use gilrs. Wasm builds use the browser's Gamepad API through `web/gamepad.js`.
+//! Controllers are polled every frame, so they can be plugged in and out while the game runs.
+
+use macroquad::prelude::*;
+
+/// How far the stick has to be pushed before it counts.
+pub const DEADZONE: f32 = 0.3;
+
+/// Buttons are numbered like the browser's standard gamepad mapping, with the stick directions after.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PadButton {
+    South = 0,
+    East = 1,
+    West = 2,
+    North = 3,
+    LeftBumper = 4,
+    RightBumper = 5,
+    Select = 8,
+    Start = 9,
+    DPadUp = 12,
+    DPadDown = 13,
+    DPadLeft = 14,
+    DPadRight = 15,
+    StickUp = 16,
+    StickDown = 17,
+    StickLeft = 18,
+    StickRight = 19,
+}
+impl PadButton {
+    pub const ALL: [PadButton; 16] = [
+        PadButton::South,
+        PadButton::East,
+        PadButton::West,
+        PadButton::North,
+        PadButton::LeftBumper,
+        PadButton::RightBumper,
+        PadButton::Select,
+        PadButton::Start,
+        PadButton::DPadUp,
+        PadButton::DPadDown,
+        PadButton::DPadLeft,
+        PadButton::DPadRight,
+        PadButton::StickUp,
+        PadButton::StickDown,
+        PadButton::StickLeft,
+        PadButton::StickRight,
+    ];
+    fn bit(self) -> u32 {
+        1 << self as u32
+    }
+    /// Name shown in game, using the labels of an xbox controller.
+    pub fn display_name(self) -> &'static str {
+        match self {
+            PadButton::South => "a",
+            PadButton::East => "b",
+            PadButton::West => "x",
+            PadButton::North => "y",
+            PadButton::LeftBumper => "lb",
+            PadButton::RightBumper => "rb",
+            PadButton::Select => "select",
+            PadButton::Start => "start",
+            PadButton::DPadUp => "dpad up",
+            PadButton::DPadDown => "dpad down",
+            PadButton::DPadLeft => "dpad left",
+            PadButton::DPadRight => "dpad right",
+            PadButton::StickUp => "stick up",
+            PadButton::StickDown => "stick down",
+            PadButton::StickLeft => "stick left",
+            PadButton::StickRight => "stick right",
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Gamepad {
+    platform: platform::Gamepads,
+    /// Bitmask of held buttons, combined over every controller.
+    down: u32,
+    last_down: u32,
+    /// Left stick, with positive y pointing down.
+    stick: Vec2,
+    connected: bool,
+}
+impl Gamepad {
+    /// Polls every controller. Should be called once per frame.
+    pub fn update(&mut self) {
+        let state = self.platform.poll();
+        self.last_down = self.down;
+        self.down = state.buttons;
+        self.stick = if state.stick.length() < DEADZONE {
+            Vec2::ZERO
+        } else {
+            state.stick.clamp_length_max(1.0)
+        };
+        for (button, active) in [
+            (PadButton::StickUp, self.stick.y < -DEADZONE),
+            (PadButton::StickDown, self.stick.y > DEADZONE),
+            (PadButton::StickLeft, self.stick.x < -DEADZONE),
+            (PadButton::StickRight, self.stick.x > DEADZONE),
+        ] {
+            if active {
+                self.down |= button.bit();
+            }
+        }
+        if state.connected != self.connected {
+            self.connected = state.connected;
+            if self.connected {
+                info!("controller connected");
+            } else {
+                info!("controller disconnected");
+            }
+        }
+    }
+    pub fn down(&self, button: PadButton) -> bool {
+        self.down & button.bit() != 0
+    }
+    pub fn pressed(&self, button: PadButton) -> bool {
+        self.down(button) && self.last_down & button.bit() == 0
+    }
+    /// Whatever button was pressed this frame.
+    pub fn last_pressed(&self) -> Option<PadButton> {
+        PadButton::ALL.into_iter().find(|f| self.pressed(*f))
+    }
+    pub fn stick(&self) -> Vec2 {
+        self.stick
+    }
+    /// Whether any controller is plugged in.
+    pub fn connected(&self) -> bool {
+        self.connected
+    }
+}
+
+/// State of every controller in a single frame.
+struct PadState {
+    buttons: u32,
+    stick: Vec2,
+    connected: bool,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+mod platform {
+    use gilrs::{Axis, Button, Gilrs};
+    use macroquad::prelude::*;
+
+    use super::{PadButton, PadState};
+
+    const BUTTONS: [(Button, PadButton); 12] = [
+        (Button::South, PadButton::South),
+        (Button::East, PadButton::East),
+        (Button::West, PadButton::West),
+        (Button::North, PadButton::North),
+        (Button::LeftTrigger, PadButton::LeftBumper),
+        (Button::RightTrigger, PadButton::RightBumper),
+        (Button::Select, PadButton::Select),
+        (Button::Start, PadButton::Start),
+        (Button::DPadUp, PadButton::DPadUp),
+        (Button::DPadDown, PadButton::DPadDown),
+        (Button::DPadLeft, PadButton::DPadLeft),
+        (Button::DPadRight, PadButton::DPadRight),
+    ];
+
+    /// `None` if controllers aren't supported on this system.
+    pub struct Gamepads(Option<Gilrs>);
+    impl Default for Gamepads {
+        fn default() -> Self {
+            match Gilrs::new() {
+                Ok(gilrs) => Self(Some(gilrs)),
+                Err(err) => {
+                    error!("controllers unavailable: {err}");
+                    Self(None)
+                }
+            }
+        }
+    }
+    impl Gamepads {
+        pub fn poll(&mut self) -> PadState {
+            let mut state = PadState {
+                buttons: 0,
+                stick: Vec2::ZERO,
+                connected: false,
+            };
+            let Some(gilrs) = &mut self.0 else {
+                return state;
+            };
+            // events have to be drained for gilrs to update its state and notice new controllers
+            while gilrs.next_event().is_some() {}
+            for (_, gamepad) in gilrs.gamepads() {
+                state.connected = true;
+                for (button, pad_button) in BUTTONS {
+                    if gamepad.is_pressed(button) {
+                        state.buttons |= 1 << pad_button as u32;
+                    }
+                }
+                // gilrs has y pointing up
+                let stick = vec2(
+                    gamepad.value(Axis::LeftStickX),
+                    -gamepad.value(Axis::LeftStickY),
+                );
+                if stick.length() > state.stick.length() {
+                    state.stick = stick;
+                }
+            }
+            state
+        }
+    }
+}
+
+/// Uses the `pumpkin_gamepad` plugin in `web/gamepad.js`.
+#[cfg(target_arch = "wasm32")]
+mod platform {
+    use macroquad::prelude::*;
+
+    use super::PadState;
+
+    unsafe extern "C" {
+        /// Number of connected controllers.
+        fn pumpkin_gamepad_count() -> u32;
+        /// Bitmask of held buttons in the standard mapping, over every controller.
+        fn pumpkin_gamepad_buttons() -> u32;
+        /// Value of an axis in the standard mapping, from whichever controller pushes it furthest.
+        fn pumpkin_gamepad_axis(axis: u32) -> f32;
+    }
+
+    /// Lets the plugin loader in `gl.js` know the plugin is in use.
+    #[unsafe(no_mangle)]
+    pub extern "C" fn pumpkin_gamepad_crate_version() -> u32 {
+        1
+    }
+
+    #[derive(Default)]
+    pub struct Gamepads;
+    impl Gamepads {
+        pub fn poll(&mut self) -> PadState {
+            unsafe {
+                PadState {
+                    buttons: pumpkin_gamepad_buttons(),
+                    stick: vec2(pumpkin_gamepad_axis(0), pumpkin_gamepad_axis(1)),
+                    connected: pumpkin_gamepad_count() > 0,
+                }
+            }
+        }
+    }
+}
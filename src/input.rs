@@ -0,0 +1,397 @@
+//! Actions the player can perform, and the keys and buttons bound to them.
+//!
+//! Bindings are stored in `bindings.toml`, as a list of key and button names for each action.
+
+use std::collections::HashMap;
+
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    gamepad::{DEADZONE, Gamepad, PadButton},
+    storage,
+};
+
+const BINDINGS_FILE: &str = "bindings.toml";
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    Up,
+    Down,
+    Jump,
+    Interact,
+    Pause,
+}
+impl Action {
+    pub const ALL: [Action; 7] = [
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::Up,
+        Action::Down,
+        Action::Jump,
+        Action::Interact,
+        Action::Pause,
+    ];
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::MoveLeft => "move left",
+            Action::MoveRight => "move right",
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::Jump => "jump",
+            Action::Interact => "interact",
+            Action::Pause => "pause",
+        }
+    }
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+/// A physical input that can trigger an action.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    Pad(PadButton),
+}
+impl Binding {
+    fn down(self, pad: &Gamepad) -> bool {
+        match self {
+            Binding::Key(key) => is_key_down(key),
+            Binding::Mouse(button) => is_mouse_button_down(button),
+            Binding::Pad(button) => pad.down(button),
+        }
+    }
+    fn pressed(self, pad: &Gamepad) -> bool {
+        match self {
+            Binding::Key(key) => is_key_pressed(key),
+            Binding::Mouse(button) => is_mouse_button_pressed(button),
+            Binding::Pad(button) => pad.pressed(button),
+        }
+    }
+    fn is_pad(self) -> bool {
+        matches!(self, Binding::Pad(_))
+    }
+    /// Name shown in game, which can be drawn with the game's font.
+    pub fn display_name(self) -> String {
+        match self {
+            Binding::Key(key) => format!("{key:?}").to_lowercase(),
+            Binding::Mouse(MouseButton::Left) => "left click".to_string(),
+            Binding::Mouse(MouseButton::Right) => "right click".to_string(),
+            Binding::Mouse(MouseButton::Middle) => "middle click".to_string(),
+            Binding::Mouse(MouseButton::Unknown) => "?".to_string(),
+            Binding::Pad(button) => button.display_name().to_string(),
+        }
+    }
+}
+impl From<Binding> for String {
+    fn from(value: Binding) -> Self {
+        match value {
+            Binding::Key(key) => format!("{key:?}"),
+            Binding::Mouse(button) => format!("Mouse{button:?}"),
+            Binding::Pad(button) => format!("Pad{button:?}"),
+        }
+    }
+}
+impl TryFrom<String> for Binding {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        if let Some(button) = value.strip_prefix("Mouse") {
+            return [MouseButton::Left, MouseButton::Right, MouseButton::Middle]
+                .into_iter()
+                .find(|f| format!("{f:?}") == button)
+                .map(Binding::Mouse)
+                .ok_or_else(|| format!("unknown mouse button \"{value}\""));
+        }
+        if let Some(button) = value.strip_prefix("Pad") {
+            return PadButton::ALL
+                .into_iter()
+                .find(|f| format!("{f:?}") == button)
+                .map(Binding::Pad)
+                .ok_or_else(|| format!("unknown controller button \"{value}\""));
+        }
+        KEYS.iter()
+            .find(|f| format!("{f:?}") == value)
+            .map(|f| Binding::Key(*f))
+            .ok_or_else(|| format!("unknown key \"{value}\""))
+    }
+}
+
+/// Input read by a single simulation tick.
+///
+/// Stored in replays as `[down, pressed, [axis x, axis y], noclip]`, with actions as bitmasks.
+#[derive(Clone, Copy, Default, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(from = "TickInputData", into = "TickInputData")]
+pub struct TickInput {
+    down: u8,
+    pressed: u8,
+    axis: Vec2,
+    /// Fly through walls. Only available on debug builds.
+    pub noclip: bool,
+}
+impl TickInput {
+    pub fn down(&self, action: Action) -> bool {
+        self.down & action.bit() != 0
+    }
+    pub fn pressed(&self, action: Action) -> bool {
+        self.pressed & action.bit() != 0
+    }
+    pub fn axis(&self) -> Vec2 {
+        self.axis
+    }
+    /// `axis` with each component snapped to -1, 0 or 1, ignoring the stick's deadzone.
+    pub fn direction(&self) -> Vec2 {
+        let snap = |f: f32| if f.abs() < DEADZONE { 0.0 } else { f.signum() };
+        vec2(snap(self.axis.x), snap(self.axis.y))
+    }
+    /// Input holding `down` and pressing `pressed`, for scripting the simulation.
+    #[cfg(test)]
+    pub fn new(down: &[Action], pressed: &[Action]) -> Self {
+        let mut input = Self::default();
+        for action in down {
+            input.down |= action.bit();
+        }
+        for action in pressed {
+            input.down |= action.bit();
+            input.pressed |= action.bit();
+        }
+        let held = |action| input.down(action) as i32 as f32;
+        let axis = vec2(
+            held(Action::MoveRight) - held(Action::MoveLeft),
+            held(Action::Down) - held(Action::Up),
+        );
+        input.axis = axis.normalize_or_zero();
+        input
+    }
+    /// The same input with the stick pushed to `axis`, for scripting the simulation.
+    #[cfg(test)]
+    pub fn with_axis(mut self, axis: Vec2) -> Self {
+        self.axis = axis;
+        self
+    }
+}
+type TickInputData = (u8, u8, [f32; 2], bool);
+impl From<TickInput> for TickInputData {
+    fn from(value: TickInput) -> Self {
+        (value.down, value.pressed, value.axis.into(), value.noclip)
+    }
+}
+impl From<TickInputData> for TickInput {
+    fn from((down, pressed, axis, noclip): TickInputData) -> Self {
+        Self {
+            down,
+            pressed,
+            axis: axis.into(),
+            noclip,
+        }
+    }
+}
+
+pub struct Input {
+    bindings: HashMap<Action, Vec<Binding>>,
+    pad: Gamepad,
+    /// Actions pressed since the last tick, so presses on frames without a tick aren't lost.
+    latched: u8,
+    /// Fly through walls without holding shift, on debug builds.
+    pub noclip: bool,
+}
+impl Default for Input {
+    fn default() -> Self {
+        let key = Binding::Key;
+        let pad = Binding::Pad;
+        Self {
+            bindings: HashMap::from([
+                (
+                    Action::MoveLeft,
+                    vec![
+                        key(KeyCode::A),
+                        pad(PadButton::DPadLeft),
+                        pad(PadButton::StickLeft),
+                    ],
+                ),
+                (
+                    Action::MoveRight,
+                    vec![
+                        key(KeyCode::D),
+                        pad(PadButton::DPadRight),
+                        pad(PadButton::StickRight),
+                    ],
+                ),
+                (
+                    Action::Up,
+                    vec![
+                        key(KeyCode::W),
+                        pad(PadButton::DPadUp),
+                        pad(PadButton::StickUp),
+                    ],
+                ),
+                (
+                    Action::Down,
+                    vec![
+                        key(KeyCode::S),
+                        pad(PadButton::DPadDown),
+                        pad(PadButton::StickDown),
+                    ],
+                ),
+                (
+                    Action::Jump,
+                    vec![key(KeyCode::Space), pad(PadButton::South)],
+                ),
+                (
+                    Action::Interact,
+                    vec![
+                        key(KeyCode::E),
+                        Binding::Mouse(MouseButton::Left),
+                        pad(PadButton::West),
+                    ],
+                ),
+                (
+                    Action::Pause,
+                    vec![key(KeyCode::Escape), pad(PadButton::Start)],
+                ),
+            ]),
+            pad: Gamepad::default(),
+            latched: 0,
+            noclip: false,
+        }
+    }
+}
+impl Input {
+    /// Loads the saved bindings. Actions missing from the file keep their default bindings.
+    pub fn load() -> Self {
+        let mut input = Self::default();
+        let text = match storage::read(BINDINGS_FILE) {
+            Ok(Some(text)) => text,
+            Ok(None) => return input,
+            Err(err) => {
+                error!("couldn't read bindings: {err}");
+                return input;
+            }
+        };
+        match toml::from_str::<HashMap<Action, Vec<Binding>>>(&text) {
+            Ok(bindings) => input.bindings.extend(bindings),
+            Err(err) => error!("invalid bindings, using defaults: {err}"),
+        }
+        input
+    }
+    pub fn save(&self) {
+        let result = toml::to_string(&self.bindings)
+            .map_err(|err| err.to_string())
+            .and_then(|text| storage::write(BINDINGS_FILE, &text));
+        if let Err(err) = result {
+            error!("couldn't save bindings: {err}");
+        }
+    }
+    /// Polls controllers. Should be called once per frame, before reading any input.
+    pub fn update(&mut self) {
+        self.pad.update();
+        for action in Action::ALL {
+            if self.pressed(action) {
+                self.latched |= action.bit();
+            }
+        }
+    }
+    /// Takes the input for the next simulation tick.
+    pub fn tick(&mut self) -> TickInput {
+        let mut down = 0;
+        for action in Action::ALL {
+            if self.down(action) {
+                down |= action.bit();
+            }
+        }
+        TickInput {
+            down,
+            pressed: std::mem::take(&mut self.latched),
+            axis: self.axis(),
+            // only allow noclip on debug builds
+            noclip: cfg!(debug_assertions) && (self.noclip || is_key_down(KeyCode::LeftShift)),
+        }
+    }
+    /// Forgets presses that no tick has seen yet, so presses in menus don't reach the game.
+    pub fn clear_pressed(&mut self) {
+        self.latched = 0;
+    }
+    pub fn bindings(&self, action: Action) -> &[Binding] {
+        self.bindings.get(&action).map_or(&[], Vec::as_slice)
+    }
+    /// Replaces the bindings of `action` on the same device as `binding`,
+    /// so rebinding a key keeps the controller binding and the other way around.
+    pub fn rebind(&mut self, action: Action, binding: Binding) {
+        let bindings = self.bindings.entry(action).or_default();
+        bindings.retain(|f| f.is_pad() != binding.is_pad());
+        bindings.push(binding);
+    }
+    /// Whatever key, mouse button or controller button was pressed this frame.
+    pub fn last_pressed(&self) -> Option<Binding> {
+        if let Some(key) = get_last_key_pressed() {
+            return Some(Binding::Key(key));
+        }
+        if let Some(button) = self.pad.last_pressed() {
+            return Some(Binding::Pad(button));
+        }
+        [MouseButton::Left, MouseButton::Right, MouseButton::Middle]
+            .into_iter()
+            .find(|f| is_mouse_button_pressed(*f))
+            .map(Binding::Mouse)
+    }
+    pub fn down(&self, action: Action) -> bool {
+        self.bindings(action).iter().any(|f| f.down(&self.pad))
+    }
+    pub fn pressed(&self, action: Action) -> bool {
+        self.bindings(action).iter().any(|f| f.pressed(&self.pad))
+    }
+    /// Name of the first input bound to `action`, for tooltips.
+    /// Prefers controller buttons while a controller is connected.
+    pub fn prompt(&self, action: Action) -> String {
+        let bindings = self.bindings(action);
+        bindings
+            .iter()
+            .find(|f| f.is_pad() == self.pad.connected())
+            .or(bindings.first())
+            .map_or("?".to_string(), |f| f.display_name())
+    }
+    /// Direction of the movement actions, or of the stick while it's pushed.
+    pub fn axis(&self) -> Vec2 {
+        let stick = self.pad.stick();
+        if stick != Vec2::ZERO {
+            return stick;
+        }
+        let mut i = Vec2::ZERO;
+        if self.down(Action::MoveLeft) {
+            i.x -= 1.0;
+        }
+        if self.down(Action::MoveRight) {
+            i.x += 1.0;
+        }
+        if self.down(Action::Up) {
+            i.y -= 1.0;
+        }
+        if self.down(Action::Down) {
+            i.y += 1.0;
+        }
+        if i.length() == 0.0 { i } else { i.normalize() }
+    }
+}
+
+/// Every key that can be bound, used to look keys up by name.
+#[rustfmt::skip]
+const KEYS: &[KeyCode] = {
+    use KeyCode::*;
+    &[
+        Space, Apostrophe, Comma, Minus, Period, Slash, Key0, Key1, Key2, Key3, Key4, Key5, Key6,
+        Key7, Key8, Key9, Semicolon, Equal, A, B, C, D, E, F, G, H, I, J, K, L, M, N, O, P, Q, R,
+        S, T, U, V, W, X, Y, Z, LeftBracket, Backslash, RightBracket, GraveAccent, World1, World2,
+        Escape, Enter, Tab, Backspace, Insert, Delete, Right, Left, Down, Up, PageUp, PageDown,
+        Home, End, CapsLock, ScrollLock, NumLock, PrintScreen, Pause, F1, F2, F3, F4, F5, F6, F7,
+        F8, F9, F10, F11, F12, F13, F14, F15, F16, F17, F18, F19, F20, F21, F22, F23, F24, F25,
+        Kp0, Kp1, Kp2, Kp3, Kp4, Kp5, Kp6, Kp7, Kp8, Kp9, KpDecimal, KpDivide, KpMultiply,
+        KpSubtract, KpAdd, KpEnter, KpEqual, LeftShift, LeftControl, LeftAlt, LeftSuper,
+        RightShift, RightControl, RightAlt, RightSuper, Menu, Back,
+    ]
+};
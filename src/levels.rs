@@ -0,0 +1,92 @@
+//! Registry of every level in the game.
+
+use std::fmt;
+
+use crate::{asset_source::AssetSource, assets::World, tiled::TiledError};
+
+struct LevelEntry {
+    name: &'static str,
+    /// Path of the map, relative to the assets directory.
+    path: &'static str,
+}
+
+pub struct LevelRegistry {
+    /// Levels in the order they're listed. The first level is where the game starts.
+    levels: Vec<LevelEntry>,
+}
+impl Default for LevelRegistry {
+    fn default() -> Self {
+        Self {
+            levels: vec![
+                LevelEntry {
+                    name: "world",
+                    path: "world/world.tmx",
+                },
+                LevelEntry {
+                    name: "cellar",
+                    path: "world/cellar.tmx",
+                },
+            ],
+        }
+    }
+}
+impl LevelRegistry {
+    pub fn first(&self) -> &'static str {
+        self.levels[0].name
+    }
+    pub fn names(&self) -> impl Iterator<Item = &'static str> {
+        self.levels.iter().map(|f| f.name)
+    }
+    /// Path of the map of the level `name`, relative to the assets directory.
+    pub fn path(&self, name: &str) -> Option<&'static str> {
+        self.levels.iter().find(|f| f.name == name).map(|f| f.path)
+    }
+    pub fn load(&self, source: &AssetSource, name: &str) -> Result<World, LevelError> {
+        let level = self
+            .levels
+            .iter()
+            .find(|f| f.name == name)
+            .ok_or_else(|| LevelError::Unknown(name.to_string()))?;
+        let tmx = source
+            .read_string(level.path)
+            .ok_or(LevelError::Missing(level.path))?;
+        // tilesets are referenced relative to the map
+        let dir = level.path.rsplit_once('/').map_or("", |(dir, _)| dir);
+        World::from_tmx(&tmx, |tileset| {
+            source.read_string(format!("{dir}/{tileset}").trim_start_matches('/'))
+        })
+        .map_err(LevelError::Tiled)
+    }
+    /// Writes the changes made to `world` into the map of the level `name`.
+    pub fn save(&self, source: &AssetSource, name: &str, world: &World) -> Result<(), LevelError> {
+        let level = self
+            .levels
+            .iter()
+            .find(|f| f.name == name)
+            .ok_or_else(|| LevelError::Unknown(name.to_string()))?;
+        let tmx = source
+            .read_string(level.path)
+            .ok_or(LevelError::Missing(level.path))?;
+        let tmx = world.write_tmx(&tmx).map_err(LevelError::Tiled)?;
+        source.write(level.path, &tmx).map_err(LevelError::Write)
+    }
+}
+
+#[derive(Debug)]
+pub enum LevelError {
+    Unknown(String),
+    Missing(&'static str),
+    Tiled(TiledError),
+    Write(String),
+}
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelError::Unknown(name) => write!(f, "no level named \"{name}\""),
+            LevelError::Missing(path) => write!(f, "couldn't read \"{path}\""),
+            LevelError::Tiled(err) => err.fmt(f),
+            LevelError::Write(err) => write!(f, "couldn't write map: {err}"),
+        }
+    }
+}
+impl std::error::Error for LevelError {}
@@ -1,156 +1,668 @@
 use macroquad::{miniquad::window::screen_size, prelude::*};
 
+use asset_source::AssetSource;
 use assets::*;
-use player::*;
+use input::{Action, Input, TickInput};
+use levels::*;
+use replay::{Replay, ReplayError};
+use save::{SaveData, SaveError};
+use simulation::{Event, Simulation};
 use utils::*;
 
-use crate::utils::create_camera;
+use crate::{
+    console::Console, debug::DebugOverlay, editor::Editor, physics::PhysicsConfig, player::Player,
+    render::WorldRenderer, utils::create_camera,
+};
 
+mod asset_source;
 mod assets;
+mod commands;
+mod console;
+mod debug;
+mod editor;
+mod gamepad;
+mod input;
+mod levels;
 mod physics;
 mod player;
+mod render;
+mod replay;
+mod save;
+mod simulation;
+mod storage;
+mod tiled;
 mod utils;
 
-struct PumpkinEngine<'a> {
-    assets: &'a Assets,
-    player: Player,
-    world: World,
+#[derive(Clone, Copy, PartialEq)]
+enum GameState {
+    Title,
+    Playing,
+    Paused,
+    /// The pumpkin guy baking a pie, after being given a pumpkin.
+    Cutscene {
+        time: f32,
+    },
+    /// Menu for rebinding inputs, opened from the pause menu.
+    Controls {
+        selected: usize,
+        rebinding: bool,
+    },
+    Win,
+    /// Level editor, only in debug builds.
+    Editor,
+}
+
+struct PumpkinEngine {
+    source: AssetSource,
+    assets: Assets,
+    levels: LevelRegistry,
+    simulation: Simulation,
+    /// Constants new and loaded games run with. Replays are played back with the ones they were
+    /// recorded with instead.
+    physics: PhysicsConfig,
     pixel_camera: Camera2D,
     frame: u32,
-    /// Camera used to render the world.
-    ///
-    /// World is only rendered once. It is rendered to a texture that can then be drawn every frame.
-    world_camera: Camera2D,
-    poi_location: Vec2,
-    pumpkin_guy_animation: Option<f32>,
+    world_renderer: WorldRenderer,
+    editor: Editor,
+    debug: DebugOverlay,
+    console: Console<PumpkinEngine>,
+    state: GameState,
+    input: Input,
+    /// Result of the last save or load, shown in menus.
+    status: Option<String>,
+    /// Input of every tick since the game was started or loaded.
+    replay: Replay,
+    /// Input of the replay being played back, used instead of the player's input.
+    playback: Option<std::vec::IntoIter<TickInput>>,
+    /// Time that hasn't been simulated yet, always less than a tick.
+    accumulator: f32,
 }
 
-impl<'a> PumpkinEngine<'a> {
-    fn new(assets: &'a Assets) -> Self {
-        let world = World::default();
-
-        let world_width = ((world.x_max - world.x_min) * 8) as f32 + 16.0 * 8.0;
-        let world_height = ((world.y_max - world.y_min) * 8) as f32 + 16.0 * 8.0;
-
-        // render world
-        let mut world_camera = create_camera(world_width, world_height);
-        world_camera.target = vec2(
-            (world.x_min + world.x_max + 16) as f32 / 2.0 * 8.0,
-            (world.y_min + world.y_max + 16) as f32 / 2.0 * 8.0,
-        );
-        set_camera(&world_camera);
-        clear_background(BLACK.with_alpha(0.0));
-
-        for chunk in &world.background {
-            chunk.draw(assets);
-        }
-        for chunk in &world.collision {
-            chunk.draw(assets);
-        }
-        for chunk in &world.details {
-            chunk.draw(assets);
-        }
-        for chunk in &world.one_way_collision {
-            chunk.draw(assets);
-        }
-
-        let poi_location = world.get_interactable_spawn(160).unwrap();
-
-        let mut player = Player::new();
-        player.pos = vec2(0.0, -8.0);
-        player.camera_pos = vec2(0.0, -100.0);
-        player.poi_location = poi_location;
-
+impl PumpkinEngine {
+    fn new(source: AssetSource) -> Self {
+        let assets = Assets::new(&source);
+        let levels = LevelRegistry::default();
+        let level = levels.first();
+        let world = levels
+            .load(&source, level)
+            .unwrap_or_else(|err| panic!("couldn't load first level: {err}"));
+        let physics = load_physics(&source).unwrap_or_default();
+        let mut simulation = Simulation::new(level, world, None);
+        simulation.physics = physics;
         let pixel_camera = create_camera(SCREEN_WIDTH, SCREEN_HEIGHT);
+        let mut console = Console::default();
+        commands::register(&mut console);
 
         PumpkinEngine {
-            poi_location,
             frame: 0,
+            replay: Replay::new(SaveData::capture(&simulation), physics),
+            simulation,
+            physics,
+            source,
             assets,
-            world,
-            player,
+            levels,
             pixel_camera,
-            world_camera,
-            pumpkin_guy_animation: None,
+            world_renderer: WorldRenderer::default(),
+            editor: Editor::default(),
+            debug: DebugOverlay::default(),
+            console,
+            state: GameState::Title,
+            input: Input::load(),
+            status: None,
+            playback: None,
+            accumulator: 0.0,
+        }
+    }
+    /// Swaps the current world for another level, keeping the player and whatever they carry.
+    fn change_level(&mut self, level: &str, spawn: Option<&str>) -> Result<(), LevelError> {
+        let world = self.levels.load(&self.source, level)?;
+        self.world_renderer.clear();
+        self.simulation.enter_world(level, world, spawn);
+        if self.state == GameState::Editor {
+            self.open_editor();
+        }
+        Ok(())
+    }
+    fn save_game(&mut self) {
+        let save = SaveData::capture(&self.simulation);
+        self.status = Some(match save::write(&save) {
+            Ok(()) => "game saved".to_string(),
+            Err(err) => {
+                error!("couldn't save: {err}");
+                err.to_string()
+            }
+        });
+    }
+    fn load_game(&mut self) {
+        let result = save::read().and_then(|save| {
+            let world = self
+                .levels
+                .load(&self.source, &save.level)
+                .map_err(|err| SaveError::Level(err.to_string()))?;
+            Ok((save, world))
+        });
+        match result {
+            Ok((save, world)) => self.start(save.restore(world), self.physics),
+            Err(err) => {
+                error!("couldn't load save: {err}");
+                self.status = Some(err.to_string());
+            }
+        }
+    }
+    /// Replaces the game with `simulation` running with `physics`, and starts recording a new
+    /// replay from it.
+    fn start(&mut self, mut simulation: Simulation, physics: PhysicsConfig) {
+        self.world_renderer.clear();
+        simulation.physics = physics;
+        self.state = if simulation.delivered {
+            GameState::Win
+        } else {
+            GameState::Playing
+        };
+        self.replay = Replay::new(SaveData::capture(&simulation), physics);
+        self.simulation = simulation;
+        self.status = None;
+        self.playback = None;
+        self.accumulator = 0.0;
+    }
+    /// Starts a new replay from the current state, after something other than input changed the
+    /// game. Playing back a replay stops too, since it can't be followed anymore.
+    fn restart_recording(&mut self) {
+        self.replay = Replay::new(SaveData::capture(&self.simulation), self.simulation.physics);
+        self.playback = None;
+    }
+    fn save_replay(&mut self) {
+        let message = match replay::write(&self.replay) {
+            Ok(()) => format!("replay saved ({} ticks)", self.replay.ticks()),
+            Err(err) => {
+                error!("couldn't save replay: {err}");
+                err.to_string()
+            }
+        };
+        info!("{message}");
+        self.status = Some(message);
+    }
+    /// Plays back the saved replay, from the state it was recorded in.
+    fn play_replay(&mut self) {
+        let result = replay::read().and_then(|replay| {
+            let world = self
+                .levels
+                .load(&self.source, &replay.start.level)
+                .map_err(|err| ReplayError::Level(err.to_string()))?;
+            Ok((replay, world))
+        });
+        match result {
+            Ok((replay, world)) => {
+                self.start(replay.start.restore(world), replay.physics);
+                self.playback = Some(replay.inputs().collect::<Vec<_>>().into_iter());
+            }
+            Err(err) => {
+                error!("couldn't play replay: {err}");
+                self.status = Some(err.to_string());
+            }
+        }
+    }
+    /// Reloads any assets that were modified on disk. Does nothing when assets are embedded.
+    fn hot_reload(&mut self) {
+        let mut redraw_world = false;
+        let mut reload_world = false;
+        let level_path = self.levels.path(&self.simulation.level);
+        for path in self.source.changed() {
+            info!("reloading {path}");
+            if self.assets.reload(&self.source, &path) {
+                redraw_world |= path == "tileset.ase";
+            } else if path == PhysicsConfig::PATH {
+                // keep the old constants while the file is broken
+                if let Some(physics) = load_physics(&self.source) {
+                    self.physics = physics;
+                    self.simulation.physics = physics;
+                    self.restart_recording();
+                }
+            } else {
+                // tilesets can be shared, so any of them might be the current level's
+                reload_world |= Some(path.as_str()) == level_path || path.ends_with(".tsx");
+            }
+        }
+        if reload_world {
+            let level = &self.simulation.level;
+            match self.levels.load(&self.source, level) {
+                Ok(world) => {
+                    // whatever changed the map has to be in what the editor saves too, unless
+                    // that would throw away edits
+                    if self.editor.unsaved(level) {
+                        self.status = Some("level changed on disk, not reloaded".to_string());
+                    } else if self.editor.map(level).is_some()
+                        && let Ok(map) = self.levels.load(&self.source, level)
+                    {
+                        self.editor.open(level, map);
+                    }
+                    self.simulation.reload_world(world);
+                    self.restart_recording();
+                    redraw_world = true;
+                }
+                Err(err) => error!("couldn't reload level \"{level}\": {err}"),
+            }
+        }
+        if redraw_world {
+            self.world_renderer.clear();
+        }
+    }
+    /// Switches to the editor, starting from the current level's map unless it's being edited
+    /// already.
+    fn open_editor(&mut self) {
+        let level = &self.simulation.level;
+        if self.editor.map(level).is_none() {
+            match self.levels.load(&self.source, level) {
+                Ok(map) => self.editor.open(level, map),
+                Err(err) => {
+                    error!("couldn't load level \"{level}\" to edit: {err}");
+                    self.status = Some(err.to_string());
+                    return;
+                }
+            }
+        }
+        // drawn from the map instead of the world being played
+        self.world_renderer.clear();
+        self.state = GameState::Editor;
+        self.editor.camera = self.pixel_camera.target;
+        self.status = None;
+    }
+    /// Writes the edits made in the editor into the current level's map.
+    fn save_level(&mut self) {
+        let level = &self.simulation.level;
+        let Some(map) = self.editor.map(level) else {
+            return;
+        };
+        let message = match self.levels.save(&self.source, level, map) {
+            Ok(()) => {
+                self.editor.saved();
+                "level saved".to_string()
+            }
+            Err(err) => {
+                error!("couldn't save level \"{level}\": {err}");
+                err.to_string()
+            }
+        };
+        self.status = Some(message);
+    }
+    /// Position of the mouse in the world.
+    fn mouse_in_world(&self) -> Vec2 {
+        let screen = Vec2::from(mouse_position()) / scale_factor();
+        screen - vec2(SCREEN_WIDTH, SCREEN_HEIGHT) / 2.0 + self.pixel_camera.target
+    }
+    /// Starts the game over from the first level.
+    fn restart(&mut self) {
+        let level = self.levels.first();
+        match self.levels.load(&self.source, level) {
+            Ok(world) => self.start(Simulation::new(level, world, None), self.physics),
+            Err(err) => error!("couldn't load level \"{level}\": {err}"),
+        }
+    }
+    fn handle_input(&mut self) {
+        // replays can be saved and played from anywhere, to make reporting bugs easy
+        if is_key_pressed(KeyCode::F5) {
+            self.save_replay();
+        } else if is_key_pressed(KeyCode::F6) {
+            self.play_replay();
+        }
+        if cfg!(debug_assertions) && is_key_pressed(KeyCode::F2) {
+            match self.state {
+                GameState::Playing | GameState::Paused => self.open_editor(),
+                GameState::Editor => {
+                    self.world_renderer.clear();
+                    self.state = GameState::Playing;
+                    self.status = None;
+                    // the level was changed under the replay
+                    self.restart_recording();
+                }
+                _ => {}
+            }
+            return;
+        }
+        match self.state {
+            GameState::Title => {
+                if self.input.pressed(Action::Jump) || is_key_pressed(KeyCode::Enter) {
+                    self.state = GameState::Playing;
+                    self.status = None;
+                } else if is_key_pressed(KeyCode::L) {
+                    self.load_game();
+                }
+            }
+            GameState::Playing => {
+                if self.input.pressed(Action::Pause) {
+                    self.state = GameState::Paused;
+                }
+            }
+            GameState::Paused => {
+                if self.input.pressed(Action::Pause) {
+                    self.state = GameState::Playing;
+                    self.status = None;
+                } else if is_key_pressed(KeyCode::C) {
+                    self.state = GameState::Controls {
+                        selected: 0,
+                        rebinding: false,
+                    };
+                } else if is_key_pressed(KeyCode::S) {
+                    self.save_game();
+                } else if is_key_pressed(KeyCode::L) {
+                    self.load_game();
+                } else if is_key_pressed(KeyCode::R) {
+                    self.restart();
+                }
+            }
+            GameState::Controls {
+                selected,
+                rebinding: true,
+            } => {
+                if is_key_pressed(KeyCode::Escape) {
+                    self.state = GameState::Controls {
+                        selected,
+                        rebinding: false,
+                    };
+                } else if let Some(binding) = self.input.last_pressed() {
+                    self.input.rebind(Action::ALL[selected], binding);
+                    self.state = GameState::Controls {
+                        selected,
+                        rebinding: false,
+                    };
+                }
+            }
+            GameState::Controls {
+                selected,
+                rebinding: false,
+            } => {
+                let count = Action::ALL.len();
+                if self.input.pressed(Action::Pause) {
+                    self.input.save();
+                    self.state = GameState::Paused;
+                } else if is_key_pressed(KeyCode::Enter) {
+                    self.state = GameState::Controls {
+                        selected,
+                        rebinding: true,
+                    };
+                } else if self.input.pressed(Action::Up) {
+                    self.state = GameState::Controls {
+                        selected: (selected + count - 1) % count,
+                        rebinding: false,
+                    };
+                } else if self.input.pressed(Action::Down) {
+                    self.state = GameState::Controls {
+                        selected: (selected + 1) % count,
+                        rebinding: false,
+                    };
+                }
+            }
+            GameState::Cutscene { .. } => {}
+            GameState::Win => {
+                if is_key_pressed(KeyCode::R) {
+                    self.restart();
+                }
+            }
+            GameState::Editor => {
+                let mouse = self.mouse_in_world();
+                let level = &self.simulation.level;
+                let world = &mut self.simulation.world;
+                self.editor.update(level, world, mouse, get_frame_time());
+                self.simulation.player.poi_location = self.simulation.poi_location();
+                if is_key_down(KeyCode::LeftControl) && is_key_pressed(KeyCode::S) {
+                    self.save_level();
+                }
+            }
         }
     }
     fn update(&mut self) {
         self.frame += 1;
+        self.hot_reload();
+
+        let frame_time = get_frame_time().min(MAX_FRAME_TIME);
+
+        // only simulate if the game was already running at the start of the frame,
+        // so keys that change state aren't also handled by the player
+        if cfg!(debug_assertions) && is_key_pressed(KeyCode::GraveAccent) {
+            self.console.toggle();
+        }
+        let was_playing = self.state == GameState::Playing && !self.console.open;
+        self.input.update();
+        if self.console.open {
+            // the console needs the engine, which it's part of
+            let mut console = std::mem::take(&mut self.console);
+            console.update(self);
+            self.console = console;
+        } else {
+            self.handle_input();
+        }
+        let playing = was_playing && self.state == GameState::Playing && !self.console.open;
+        self.debug.update(self.pixel_camera.target, scale_factor());
 
-        // cap delta time to a minimum of 60 fps.
-        let delta_time = get_frame_time().min(1.0 / 60.0);
-        let (actual_screen_width, actual_screen_height) = screen_size();
-        let scale_factor =
-            (actual_screen_width / SCREEN_WIDTH).min(actual_screen_height / SCREEN_HEIGHT);
-        self.player.update(&mut self.world, delta_time);
-        self.pixel_camera.target = self.player.camera_pos.floor();
+        if playing {
+            self.accumulator += frame_time;
+            while self.accumulator >= TICK && self.state == GameState::Playing {
+                // the player's input is still taken during replays, so presses don't pile up
+                let live = self.input.tick();
+                let input = self
+                    .playback
+                    .as_mut()
+                    .and_then(Iterator::next)
+                    .unwrap_or(live);
+                if self.playback.as_ref().is_some_and(|f| f.len() == 0) {
+                    info!("replay finished");
+                    self.playback = None;
+                }
+                self.replay.record(input);
+                match self.simulation.tick(&input) {
+                    Some(Event::Delivered) => {
+                        self.state = GameState::Cutscene { time: 0.0 };
+                        // don't overwrite the player's save with a replay
+                        if self.playback.is_none() {
+                            self.save_game();
+                        }
+                    }
+                    Some(Event::Exit { level, spawn }) => {
+                        if let Err(err) = self.change_level(&level, spawn.as_deref()) {
+                            error!("couldn't load level \"{level}\": {err}");
+                        }
+                    }
+                    None => {}
+                }
+                self.accumulator -= TICK;
+            }
+        } else {
+            self.input.clear_pressed();
+        }
+        if let GameState::Cutscene { time } = &mut self.state {
+            *time += frame_time;
+            if *time * 1000.0 > self.assets.pumpkin_guy_animation.total_length as f32 {
+                self.state = GameState::Win;
+            }
+        }
+
+        self.draw(playing, self.accumulator / TICK);
+    }
+    /// Draws the game, `alpha` of the way from the last tick to the next.
+    /// Tooltips are only shown if `playing`.
+    fn draw(&mut self, playing: bool, alpha: f32) {
+        // only one of them is drawn, but the renderer is cleared when switching between them
+        let mut changed = self.simulation.world.take_changed();
+        changed.extend(self.editor.take_changed());
+        for region in changed {
+            self.world_renderer.redraw(region);
+        }
+        let scale_factor = scale_factor();
+        let player = &self.simulation.player;
+        // the editor shows the map it's editing, with pumpkins where they start out
+        let world = match self.state {
+            GameState::Editor => self.editor.map(&self.simulation.level),
+            _ => None,
+        }
+        .unwrap_or(&self.simulation.world);
+        let poi = world.npc("pumpkin_guy");
+        let free_camera = self.debug.free_camera.as_ref();
+        let (target, zoom) = match free_camera {
+            _ if self.state == GameState::Editor => (self.editor.camera, 1.0),
+            Some(camera) => (camera.pos, camera.zoom),
+            None => (player.interpolated_camera_pos(alpha), 1.0),
+        };
+        self.pixel_camera.target = target.floor();
+        let screen = vec2(SCREEN_WIDTH, SCREEN_HEIGHT);
+        self.pixel_camera.zoom = 2.0 / screen * zoom;
+        let view = Rect::new(
+            self.pixel_camera.target.x - screen.x / zoom / 2.0,
+            self.pixel_camera.target.y - screen.y / zoom / 2.0,
+            screen.x / zoom,
+            screen.y / zoom,
+        );
+        self.world_renderer.update(&self.assets, world, view);
         set_camera(&self.pixel_camera);
         clear_background(Color::from_hex(0x567c7d));
 
-        // position world texture
-        draw_texture_ex(
-            &self.world_camera.render_target.as_ref().unwrap().texture,
-            (self.world.x_min) as f32 * 8.0,
-            (self.world.y_min) as f32 * 8.0,
-            WHITE,
-            DrawTextureParams::default(),
-        );
-        draw_texture(
-            self.assets.poi.get_at_time((get_time() * 1000.0) as u32),
-            self.poi_location.x,
-            self.poi_location.y - 3.0 * 8.0,
-            WHITE,
-        );
-        if let Some(pumpkin_guy_animation) = &mut self.pumpkin_guy_animation {
+        let time = (get_time() * 1000.0) as u32;
+        self.world_renderer.draw(&self.assets, world, view, time);
+        if let Some(poi_location) = poi.map(|f| f.pos) {
             draw_texture(
-                self.assets
-                    .pumpkin_guy_animation
-                    .get_at_time((*pumpkin_guy_animation * 1000.0) as u32),
-                self.poi_location.x,
-                self.poi_location.y - 24.0,
+                self.assets.poi.get_at_time((get_time() * 1000.0) as u32),
+                poi_location.x,
+                poi_location.y - 3.0 * 8.0,
                 WHITE,
             );
-            *pumpkin_guy_animation += delta_time;
-        }
-        for pumpkin in self.world.pumpkins.iter_mut() {
-            pumpkin.draw(self.assets, &self.player.pos, self.player.on_ground);
-            pumpkin.update(
-                delta_time,
-                &self.world.collision,
-                &self.world.one_way_collision,
-            );
+            if let GameState::Cutscene { time } = self.state {
+                draw_texture(
+                    self.assets
+                        .pumpkin_guy_animation
+                        .get_at_time((time * 1000.0) as u32),
+                    poi_location.x,
+                    poi_location.y - 24.0,
+                    WHITE,
+                );
+            }
         }
-        self.player.draw(self.assets);
-        if self.pumpkin_guy_animation.is_none()
-            && self.player.pos.distance(self.poi_location) <= 16.0
+        for pumpkin in world.pumpkins.iter() {
+            pumpkin.draw(&self.assets, &player.pos, player.on_ground, alpha);
+        }
+        player.draw(&self.assets, alpha);
+        if let Some(poi) = poi
+            && playing
+            && self.simulation.near_poi()
+        {
+            let poi_location = poi.pos;
+            if player.carrying.is_none() {
+                let text = poi.properties.string("dialogue").unwrap_or_default();
+                // NPCs without anything to say don't get a box
+                if let Some(longest) = text.lines().map(str::len).max() {
+                    let width = (longest + 2) as f32 * 4.0;
+                    let height = (text.lines().count() + 2) as f32 * 5.0;
+                    draw_rectangle(
+                        poi_location.x - 6.0 * 8.0,
+                        poi_location.y - 3.0 * 8.0 + 2.0,
+                        width,
+                        height,
+                        WHITE,
+                    );
+                    self.assets.draw_text(
+                        text,
+                        poi_location.x + 4.0 - 6.0 * 8.0,
+                        poi_location.y + 5.0 - 3.0 * 8.0 + 2.0,
+                    );
+                }
+            } else {
+                let text = format!("{}: give pumpkin", self.input.prompt(Action::Interact));
+                show_tooltip(&text, &self.assets, player);
+            }
+        }
+
+        if playing
+            && let Some(exit) = world.exit_at(Player::HITBOX.at(player.pos))
+            && exit.door
         {
-            if self.player.carrying.is_none() {
-                let text = "bring me a pumpkin\nand i will bake you a pie!";
-                draw_rectangle(
-                    self.poi_location.x - 6.0 * 8.0,
-                    self.poi_location.y - 3.0 * 8.0 + 2.0,
-                    ({
-                        let mut s = text.lines().collect::<Vec<&str>>();
-                        s.sort_by(|a, b| b.len().cmp(&a.len()));
-                        s
-                    }[0]
-                    .len()
-                        + 2) as f32
-                        * 4.0,
-                    (text.lines().count() + 2) as f32 * 5.0,
+            let text = format!("{}: enter", self.input.prompt(Action::Up));
+            show_tooltip(&text, &self.assets, player);
+        }
+        if self.debug.enabled {
+            self.debug.draw_world(world, player, view, alpha);
+        }
+
+        let center = self.pixel_camera.target;
+        match self.state {
+            GameState::Title | GameState::Paused => {
+                let mut text = if self.state == GameState::Title {
+                    format!(
+                        "pumpkin!\n\n{}: start\nl: load",
+                        self.input.prompt(Action::Jump)
+                    )
+                } else {
+                    format!(
+                        "paused\n\n{}: resume\ns: save\nl: load\nc: controls\nr: restart",
+                        self.input.prompt(Action::Pause)
+                    )
+                };
+                if let Some(status) = &self.status {
+                    text += "\n\n";
+                    text += status;
+                }
+                draw_text_box(&text, &self.assets, center)
+            }
+            GameState::Controls {
+                selected,
+                rebinding,
+            } => {
+                let mut text = "controls\n\n".to_string();
+                for (index, action) in Action::ALL.into_iter().enumerate() {
+                    let bindings = if rebinding && index == selected {
+                        "press a key or button...".to_string()
+                    } else {
+                        let names: Vec<String> = self
+                            .input
+                            .bindings(action)
+                            .iter()
+                            .map(|f| f.display_name())
+                            .collect();
+                        names.join("/")
+                    };
+                    let marker = if index == selected { '+' } else { '-' };
+                    text += &format!("{marker} {}: {bindings}\n", action.name());
+                }
+                text += &format!(
+                    "\nenter: rebind\n{}: back",
+                    self.input.prompt(Action::Pause)
+                );
+                draw_text_box(&text, &self.assets, center)
+            }
+            GameState::Win => {
+                draw_texture_ex(
+                    &self.assets.win_screen,
+                    center.x - SCREEN_WIDTH / 2.0,
+                    center.y - SCREEN_HEIGHT / 2.0,
                     WHITE,
+                    DrawTextureParams {
+                        dest_size: Some(vec2(SCREEN_WIDTH, SCREEN_HEIGHT)),
+                        ..Default::default()
+                    },
                 );
-                self.assets.draw_text(
-                    text,
-                    self.poi_location.x + 4.0 - 6.0 * 8.0,
-                    self.poi_location.y + 5.0 - 3.0 * 8.0 + 2.0,
+                draw_text_box(
+                    "r: play again",
+                    &self.assets,
+                    center + vec2(0.0, SCREEN_HEIGHT / 2.0 - 8.0),
                 );
-            } else {
-                if show_tooltip("e: give pumpkin", self.assets, &self.player) {
-                    self.pumpkin_guy_animation = Some(0.0);
-                    self.player.carrying = None;
-                }
             }
+            GameState::Editor => {
+                let mouse = self.mouse_in_world();
+                let status = self.status.as_deref();
+                self.editor.draw(&self.assets, world, mouse, status);
+            }
+            GameState::Playing | GameState::Cutscene { .. } => {}
+        }
+        if self.debug.enabled || self.console.open {
+            set_camera(&Camera2D {
+                target: screen / 2.0,
+                zoom: 2.0 / screen,
+                render_target: self.pixel_camera.render_target.clone(),
+                ..Default::default()
+            });
+        }
+        if self.debug.enabled {
+            self.debug.draw_hud(&self.assets, player);
+        }
+        if self.console.open {
+            self.console.draw(&self.assets);
         }
 
         set_default_camera();
@@ -168,29 +680,27 @@ impl<'a> PumpkinEngine<'a> {
                 ..Default::default()
             },
         );
-        if let Some(pumpkin_guy_animation) = &mut self.pumpkin_guy_animation {
-            if *pumpkin_guy_animation * 1000.0
-                > self.assets.pumpkin_guy_animation.total_length as f32
-            {
-                draw_texture_ex(
-                    &self.assets.win_screen,
-                    0.0,
-                    0.0,
-                    WHITE,
-                    DrawTextureParams {
-                        dest_size: Some(vec2(
-                            SCREEN_WIDTH * scale_factor,
-                            SCREEN_HEIGHT * scale_factor,
-                        )),
-                        ..Default::default()
-                    },
-                );
-            }
-        }
-        //draw_text(&get_fps().to_string(), 48.0, 48.0, 32.0, WHITE);
     }
 }
 
+/// Reads the physics constants, logging why if they can't be.
+fn load_physics(source: &AssetSource) -> Option<PhysicsConfig> {
+    let path = PhysicsConfig::PATH;
+    let Some(text) = source.read_string(path) else {
+        error!("missing \"{path}\"");
+        return None;
+    };
+    PhysicsConfig::from_toml(&text)
+        .inspect_err(|err| error!("couldn't read \"{path}\": {err}"))
+        .ok()
+}
+
+/// How many times bigger than `SCREEN_WIDTH` by `SCREEN_HEIGHT` the game is drawn in the window.
+fn scale_factor() -> f32 {
+    let (width, height) = screen_size();
+    (width / SCREEN_WIDTH).min(height / SCREEN_HEIGHT)
+}
+
 fn window_conf() -> Conf {
     Conf {
         window_title: "pumpkin".to_string(),
@@ -205,8 +715,7 @@ fn window_conf() -> Conf {
 }
 #[macroquad::main(window_conf)]
 async fn main() {
-    let assets = Assets::default();
-    let mut pumpkin_engine = PumpkinEngine::new(&assets);
+    let mut pumpkin_engine = PumpkinEngine::new(AssetSource::default());
 
     loop {
         pumpkin_engine.update();
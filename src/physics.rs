@@ -1,151 +1,1023 @@
-use crate::assets::{Chunk, Pumpkin};
+use std::collections::HashMap;
+
+use crate::assets::{Layer, TileProperties};
 use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Size of a tile, in pixels.
+const TILE_SIZE: f32 = 8.0;
+/// Furthest a body moves in one step, so that it can't skip over a tile.
+const MAX_STEP: f32 = TILE_SIZE / 2.0;
+/// Most steps a body is moved in per update. Anything faster is slowed down to this, so a huge
+/// velocity can't stall the game.
+const MAX_STEPS: f32 = 256.0;
+/// Leeway for rounding errors when checking whether a body is already past a tile.
+const EPSILON: f32 = 0.001;
+/// How far a body on a slope can be moved up or down to keep it on the ground.
+const STEP_HEIGHT: f32 = TILE_SIZE / 2.0;
+/// Slowest a body bounces off bouncy tiles, so that it can come to rest on them.
+const MIN_BOUNCE_SPEED: f32 = 30.0;
+
+/// Constants that bodies move by, read from `physics.toml` so they can be tuned without
+/// recompiling. Anything missing from the file keeps its default.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct PhysicsConfig {
+    pub gravity: f32,
+    /// Fastest the player runs.
+    pub max_velocity: f32,
+    pub ground_friction: f32,
+    pub air_drag: f32,
+    pub climb_speed: f32,
+    /// Force the player runs with.
+    pub run_force: f32,
+    /// How much of `run_force` the player keeps in the air, when moving the way they're going.
+    pub air_control: f32,
+    /// Upwards speed the player jumps with.
+    pub jump_impulse: f32,
+    /// Seconds after jumping that letting go of jump still cuts the jump short.
+    pub jump_window: f32,
+    /// How much of the player's upwards speed is kept when they let go of jump early.
+    pub jump_cut: f32,
+    /// Seconds after walking off a ledge that the player can still jump.
+    pub coyote_time: f32,
+    /// Seconds before landing that pressing jump still jumps once the player lands.
+    pub jump_buffer: f32,
+    /// Speed pumpkins are thrown at.
+    pub throw_speed: f32,
+}
+impl Default for PhysicsConfig {
+    fn default() -> Self {
+        Self {
+            gravity: 900.0,
+            max_velocity: 90.0,
+            ground_friction: 12.6,
+            air_drag: 0.6,
+            climb_speed: 45.0,
+            run_force: 3600.0,
+            air_control: 0.02,
+            jump_impulse: 180.0,
+            jump_window: 0.5,
+            jump_cut: 0.5,
+            coyote_time: 0.1,
+            jump_buffer: 0.1,
+            throw_speed: 180.0,
+        }
+    }
+}
+impl PhysicsConfig {
+    pub const PATH: &str = "physics.toml";
+
+    pub fn from_toml(text: &str) -> Result<Self, String> {
+        let config: Self = toml::from_str(text).map_err(|err| err.to_string())?;
+        match config
+            .to_table()
+            .into_iter()
+            .find(|(_, value)| !value.as_float().is_some_and(f64::is_finite))
+        {
+            Some((name, value)) => Err(format!("\"{name}\" isn't a number: {value}")),
+            None => Ok(config),
+        }
+    }
+    fn to_table(self) -> toml::Table {
+        toml::Table::try_from(self).expect("constants are always valid toml")
+    }
+    /// Names of the constants, as written in the file.
+    pub fn names(&self) -> Vec<String> {
+        self.to_table().keys().cloned().collect()
+    }
+    pub fn get(&self, name: &str) -> Option<f32> {
+        self.to_table()
+            .get(name)
+            .and_then(toml::Value::as_float)
+            .map(|f| f as f32)
+    }
+    pub fn set(&mut self, name: &str, value: f32) -> Result<(), String> {
+        let mut table = self.to_table();
+        if !table.contains_key(name) {
+            return Err(format!("unknown constant \"{name}\""));
+        }
+        if !value.is_finite() {
+            return Err(format!("\"{name}\" isn't a number: {value}"));
+        }
+        table.insert(name.to_string(), toml::Value::Float(value.into()));
+        *self = table
+            .try_into()
+            .map_err(|err: toml::de::Error| err.to_string())?;
+        Ok(())
+    }
+}
+
+/// Box that collides with tiles, relative to the position of whatever it belongs to.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hitbox {
+    pub offset: Vec2,
+    pub size: Vec2,
+}
+impl Hitbox {
+    pub const fn new(x: f32, y: f32, w: f32, h: f32) -> Self {
+        Self {
+            offset: vec2(x, y),
+            size: vec2(w, h),
+        }
+    }
+    /// The hitbox of something at `pos`.
+    pub fn at(self, pos: Vec2) -> Rect {
+        Rect::new(
+            pos.x + self.offset.x,
+            pos.y + self.offset.y,
+            self.size.x,
+            self.size.y,
+        )
+    }
+}
 
-fn get_tile(chunks: &[&Chunk], x: i16, y: i16) -> i16 {
-    let cx = ((x as f32 / 16.0).floor() * 16.0) as i16;
-    let cy = ((y as f32 / 16.0).floor() * 16.0) as i16;
-    let Some(chunk) = chunks.iter().find(|f| f.x == cx && f.y == cy) else {
-        return 0;
+/// Which sides of a body ran into something.
+#[derive(Clone, Copy, Default, Debug, PartialEq)]
+pub struct Contacts {
+    pub up: bool,
+    pub down: bool,
+    pub left: bool,
+    pub right: bool,
+    /// The tile stood on, if any.
+    pub ground: Option<TileProperties>,
+}
+impl Contacts {
+    /// How far the ground goes down for every pixel to the right. 0 on flat ground, or in the air.
+    pub fn slope(&self) -> f32 {
+        self.ground.map_or(0.0, |f| f.shape.slope())
+    }
+    /// Friction of the ground, relative to normal ground. 0 in the air.
+    pub fn friction(&self) -> f32 {
+        match self.ground {
+            Some(ground) => ground.friction,
+            // standing on something other than tiles
+            None if self.down => 1.0,
+            None => 0.0,
+        }
+    }
+}
+
+/// The solid part of a tile: everything below a line from `left` to `right`, which are heights
+/// in pixels from the bottom of the tile.
+///
+/// Only the top of a tile can be sloped, the underside is always flat.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TileShape {
+    pub left: f32,
+    pub right: f32,
+}
+impl TileShape {
+    pub const FULL: TileShape = TileShape {
+        left: TILE_SIZE,
+        right: TILE_SIZE,
     };
-    let local_x = x - chunk.x;
-    let local_y = y - chunk.y;
-    chunk.tile_at(local_x as _, local_y as _).unwrap_or(0)
+    fn is_slope(self) -> bool {
+        self.left != self.right
+    }
+    /// Height of the solid part, `x` pixels from the left of the tile.
+    fn height_at(self, x: f32) -> f32 {
+        let t = (x / TILE_SIZE).clamp(0.0, 1.0);
+        self.left + (self.right - self.left) * t
+    }
+    fn slope(self) -> f32 {
+        (self.left - self.right) / TILE_SIZE
+    }
+}
+
+/// Everything bodies collide with in a level.
+///
+/// Tiles are solid or one way depending on their layer, unless their properties say otherwise.
+pub struct Tiles<'a> {
+    pub collision: &'a Layer,
+    pub one_way: &'a Layer,
+    /// Properties of tiles that have any, by gid.
+    pub properties: &'a HashMap<i16, TileProperties>,
+}
+impl Tiles<'_> {
+    /// Tiles at `x`, `y` on either layer, and whether each is solid or only one way.
+    fn at(&self, x: i16, y: i16) -> impl Iterator<Item = (bool, TileProperties)> {
+        [(self.collision, true), (self.one_way, false)]
+            .into_iter()
+            .filter_map(move |(layer, solid)| {
+                let tile = layer.tile_at(x, y);
+                (tile != 0).then(|| {
+                    (
+                        solid,
+                        self.properties.get(&tile).copied().unwrap_or_default(),
+                    )
+                })
+            })
+    }
+    /// The tile at `x`, `y` that bodies can't pass through from any side.
+    pub fn solid(&self, x: i16, y: i16) -> Option<TileProperties> {
+        self.at(x, y)
+            .find(|(layer, f)| match (f.solid, f.one_way) {
+                (Some(solid), _) => solid,
+                (None, Some(one_way)) => !one_way,
+                (None, None) => *layer,
+            })
+            .map(|(_, f)| f)
+    }
+    /// The tile at `x`, `y` that bodies can only land on from above.
+    pub fn one_way(&self, x: i16, y: i16) -> Option<TileProperties> {
+        self.at(x, y)
+            .find(|(layer, f)| match (f.solid, f.one_way) {
+                (Some(true), _) => false,
+                (_, Some(one_way)) => one_way,
+                (_, None) => !*layer,
+            })
+            .map(|(_, f)| f)
+    }
+    /// Every tile overlapping `rect`, solid or not.
+    pub fn touching(&self, rect: Rect) -> impl Iterator<Item = TileProperties> {
+        let rows = tile_range(rect.y, rect.bottom());
+        let columns = tile_range(rect.x, rect.right());
+        rows.flat_map(move |y| columns.clone().map(move |x| (x, y)))
+            .flat_map(|(x, y)| self.at(x, y).map(|(_, f)| f))
+    }
+    /// Where a body at `min..max` would stand, within `STEP_HEIGHT` of its feet, and on what.
+    fn ground(&self, min: Vec2, max: Vec2) -> Option<(f32, TileProperties)> {
+        let mut ground: Option<(f32, TileProperties)> = None;
+        for y in tile_range(max.y - STEP_HEIGHT, max.y + STEP_HEIGHT) {
+            for x in tile_range(min.x, max.x) {
+                let tile = vec2(x as f32, y as f32) * TILE_SIZE;
+                let surface = match (self.solid(x, y), self.one_way(x, y)) {
+                    (Some(properties), _) => {
+                        let Some(y) = surface(properties.shape, tile, min.x, max.x) else {
+                            continue;
+                        };
+                        (y, properties)
+                    }
+                    // one way platforms can only be stepped down onto
+                    (None, Some(properties)) if tile.y >= max.y - EPSILON => (tile.y, properties),
+                    _ => continue,
+                };
+                if (max.y - STEP_HEIGHT..=max.y + STEP_HEIGHT).contains(&surface.0)
+                    && ground.is_none_or(|f| surface.0 < f.0)
+                {
+                    ground = Some(surface);
+                }
+            }
+        }
+        ground
+    }
+}
+
+/// Height of the top of `shape`, for a body spanning `left..right`. Slopes only hold up bodies
+/// whose center is above them.
+fn surface(shape: TileShape, tile: Vec2, left: f32, right: f32) -> Option<f32> {
+    let bottom = tile.y + TILE_SIZE;
+    if !shape.is_slope() {
+        return Some(bottom - shape.left);
+    }
+    let center = (left + right) / 2.0 - tile.x;
+    (0.0..TILE_SIZE)
+        .contains(&center)
+        .then(|| bottom - shape.height_at(center))
+}
+
+/// Tiles covering `start..end` along one axis, as a range of tile coordinates.
+fn tile_range(start: f32, end: f32) -> std::ops::Range<i16> {
+    (start / TILE_SIZE).floor() as i16..(end / TILE_SIZE).ceil() as i16
 }
 
-fn ceil_g(a: f32) -> f32 {
-    if a < 0.0 { a.floor() } else { a.ceil() }
+/// How far the box at `min..max` can move along `axis` towards `delta`, if a tile or body is in the way.
+///
+/// One way tiles only stop boxes falling onto them, and pop up boxes whose bottom has sunk into them.
+/// Slopes do the same, and only stop boxes moving sideways at their high end. Boxes `on_slope`
+/// can step over anything up to `STEP_HEIGHT` high.
+fn sweep(
+    min: Vec2,
+    max: Vec2,
+    axis: usize,
+    delta: f32,
+    tiles: &Tiles,
+    bodies: &[Rect],
+    on_slope: bool,
+) -> Option<f32> {
+    let direction = delta.signum();
+    // the area swept over, along the axis
+    let (start, end) = if delta > 0.0 {
+        (max[axis], max[axis] + delta)
+    } else {
+        (min[axis] + delta, min[axis])
+    };
+    let across = 1 - axis;
+    let mut hit: Option<f32> = None;
+    let mut block = |allowed: f32, leeway: f32| {
+        if allowed * direction >= -leeway && hit.is_none_or(|f| allowed * direction < f * direction)
+        {
+            hit = Some(allowed);
+        }
+    };
+    for along in tile_range(start, end) {
+        for side in tile_range(min[across], max[across]) {
+            let (x, y) = if axis == 0 {
+                (along, side)
+            } else {
+                (side, along)
+            };
+            let tile = vec2(x as f32, y as f32) * TILE_SIZE;
+            let near_edge = if delta > 0.0 {
+                tile[axis]
+            } else {
+                tile[axis] + TILE_SIZE
+            };
+            let allowed = near_edge - if delta > 0.0 { max[axis] } else { min[axis] };
+            let Some(TileProperties { shape, .. }) = tiles.solid(x, y) else {
+                if axis == 1 && delta > 0.0 && tiles.one_way(x, y).is_some() {
+                    block(allowed, TILE_SIZE - EPSILON);
+                }
+                continue;
+            };
+            match (axis, delta > 0.0) {
+                (0, _) => {
+                    // the side of the tile that's run into
+                    let height = if delta > 0.0 { shape.left } else { shape.right };
+                    let feet = if on_slope { max.y - STEP_HEIGHT } else { max.y };
+                    if height > 0.0 && tile.y + TILE_SIZE - height < feet - EPSILON {
+                        // tiles the box is already inside of don't stop it, so it can get back out
+                        block(allowed, EPSILON);
+                    }
+                }
+                (_, true) => {
+                    let Some(surface) = surface(shape, tile, min.x, max.x) else {
+                        continue;
+                    };
+                    let leeway = if shape.is_slope() { TILE_SIZE } else { EPSILON };
+                    block(surface - max.y, leeway);
+                }
+                (_, false) => block(allowed, EPSILON),
+            }
+        }
+    }
+    for body in bodies {
+        let (body_min, body_max) = (body.point(), body.point() + body.size());
+        if body_min[across] >= max[across] || body_max[across] <= min[across] {
+            continue;
+        }
+        let allowed = if delta > 0.0 {
+            body_min[axis] - max[axis]
+        } else {
+            body_max[axis] - min[axis]
+        };
+        block(allowed, EPSILON);
+    }
+    hit.filter(|f| f * direction <= delta.abs())
 }
 
+/// Moves a body with `hitbox` along `velocity`, stopping it at any tiles or other `bodies` in the way.
+///
+/// Fast bodies are moved in several steps, so they can't pass through tiles. Velocity into a
+/// tile that was hit is cancelled, unless it's bouncy. Bodies on the ground stick to it when walking
+/// up or down slopes.
 pub fn update_physicsbody(
     pos: Vec2,
     velocity: &mut Vec2,
+    hitbox: Hitbox,
     delta_time: f32,
-    collision_tiles: &[Chunk],
-    one_way_tiles: &[Chunk],
-) -> (Vec2, bool) {
-    let mut new = pos + *velocity * delta_time;
-
-    let tile_x = pos.x / 8.0;
-    let tile_y = pos.y / 8.0;
-
-    let tiles_y = [
-        (tile_x.trunc(), ceil_g(new.y / 8.0)),
-        (ceil_g(tile_x), ceil_g(new.y / 8.0)),
-        (tile_x.trunc(), (new.y / 8.0).trunc()),
-        (ceil_g(tile_x), (new.y / 8.0).trunc()),
-    ];
-
-    let chunks_pos: [(i16, i16); 4] = std::array::from_fn(|f| {
-        let cx = ((tiles_y[f].0 / 16.0).floor() * 16.0) as i16;
-        let cy = ((tiles_y[f].1 / 16.0).floor() * 16.0) as i16;
-        (cx, cy)
-    });
-
-    let chunks: Vec<&Chunk> = collision_tiles
-        .iter()
-        .filter(|f| chunks_pos.contains(&(f.x, f.y)))
-        .collect();
-
-    let one_way_chunks: Vec<&Chunk> = one_way_tiles
-        .iter()
-        .filter(|f| chunks_pos.contains(&(f.x, f.y)))
-        .collect();
-
-    let mut on_ground = false;
-    for (tx, ty) in tiles_y {
-        let tile = get_tile(&chunks, tx as i16, ty as i16);
-        if tile != 0 {
-            let c = if velocity.y < 0.0 {
-                tile_y.floor() * 8.0
+    tiles: &Tiles,
+    bodies: &[Rect],
+) -> (Vec2, Contacts) {
+    let mut pos = pos;
+    let mut contacts = Contacts::default();
+    if !velocity.is_finite() {
+        *velocity = Vec2::ZERO;
+        return (pos, contacts);
+    }
+    let motion = (*velocity * delta_time).clamp(
+        Vec2::splat(-MAX_STEP * MAX_STEPS),
+        Vec2::splat(MAX_STEP * MAX_STEPS),
+    );
+    let steps = (motion.abs().max_element() / MAX_STEP).ceil().max(1.0);
+    let step = motion / steps;
+
+    for _ in 0..steps as u32 {
+        // vertical first, so that clipping the corner of a ledge lands on it instead of stopping short
+        for axis in [1, 0] {
+            if step[axis] == 0.0 || velocity[axis] == 0.0 {
+                continue;
+            }
+            let min = pos + hitbox.offset;
+            let max = min + hitbox.size;
+            let on_slope = contacts.down && contacts.slope() != 0.0;
+            let Some(allowed) = sweep(min, max, axis, step[axis], tiles, bodies, on_slope) else {
+                pos[axis] += step[axis];
+                continue;
+            };
+            pos[axis] += allowed;
+            match (axis, step[axis] > 0.0) {
+                (0, false) => contacts.left = true,
+                (0, true) => contacts.right = true,
+                (_, false) => contacts.up = true,
+                (_, true) => {
+                    contacts.down = true;
+                    let min = pos + hitbox.offset;
+                    contacts.ground = tiles.ground(min, min + hitbox.size).map(|(_, f)| f);
+                }
+            }
+            let bounce = match contacts.ground {
+                Some(ground) if axis == 1 && step[axis] > 0.0 => -velocity.y * ground.bouncy,
+                _ => 0.0,
+            };
+            velocity[axis] = if bounce.abs() >= MIN_BOUNCE_SPEED {
+                bounce
             } else {
-                on_ground = true;
-                tile_y.ceil() * 8.0
+                0.0
             };
-            new.y = c;
-            velocity.y = 0.0;
-            break;
         }
 
-        // handle single way platforms
-        if velocity.y > 0.0
-            && ty.trunc() > tile_y.trunc()
-            && get_tile(&one_way_chunks, tx as i16, ty as i16) != 0
-        {
-            new.y = tile_y.ceil() * 8.0;
-            velocity.y = 0.0;
-            on_ground = true;
-            break;
-        }
-    }
-    let tiles_x = [
-        ((new.x / 8.0).trunc(), ceil_g(new.y / 8.0)),
-        (ceil_g(new.x / 8.0), ceil_g(new.y / 8.0)),
-        (ceil_g(new.x / 8.0), (new.y / 8.0).trunc()),
-        ((new.x / 8.0).trunc(), (new.y / 8.0).trunc()),
-    ];
-
-    let chunks_pos: [(i16, i16); 4] = std::array::from_fn(|f| {
-        let cx = ((tiles_x[f].0 / 16.0).floor() * 16.0) as i16;
-        let cy = ((tiles_x[f].1 / 16.0).floor() * 16.0) as i16;
-        (cx, cy)
-    });
-
-    let chunks: Vec<&Chunk> = collision_tiles
-        .iter()
-        .filter(|f| chunks_pos.contains(&(f.x, f.y)))
-        .collect();
-
-    for (tx, ty) in tiles_x {
-        let tile = get_tile(&chunks, tx as i16, ty as i16);
-        if tile != 0 {
-            let c = if velocity.x < 0.0 {
-                tile_x.floor() * 8.0
-            } else {
-                tile_x.ceil() * 8.0
-            };
-            new.x = c;
-            velocity.x = 0.0;
-            break;
+        // keep to slopes, instead of walking off into the air when going down them
+        if contacts.down && velocity.y >= 0.0 {
+            let min = pos + hitbox.offset;
+            let max = min + hitbox.size;
+            if let Some((ground, properties)) = tiles.ground(min, max)
+                && (properties.shape.slope() != 0.0 || contacts.slope() != 0.0)
+            {
+                let mut delta = ground - max.y;
+                if delta > 0.0 {
+                    // stepping down onto a slope can't go into something else on the way
+                    delta = sweep(min, max, 1, delta, tiles, bodies, false).unwrap_or(delta);
+                }
+                pos.y += delta;
+                contacts.ground = Some(properties);
+            }
         }
     }
-    (new, on_ground)
+    (pos, contacts)
+}
+
+/// How far two boxes overlap on each axis. Both are positive if they overlap.
+fn penetration(a: Rect, b: Rect) -> Vec2 {
+    vec2(
+        a.right().min(b.right()) - a.x.max(b.x),
+        a.bottom().min(b.bottom()) - a.y.max(b.y),
+    )
 }
 
+/// Pushes a body with `hitbox` out of any of the hitboxes of `pumpkins` it's inside of, deepest first, along whichever
+/// axis gets it out quickest. Velocity into a pumpkin is cancelled.
+///
+/// A body squeezed between pumpkins on both sides is centered between them, rather than pushed out.
 pub fn collide_with_pumpkins(
     mut pos: Vec2,
     velocity: &mut Vec2,
-    pumpkins: &Vec<Pumpkin>,
-) -> (Vec2, bool) {
-    let mut on_ground = false;
-    for pumpkin in pumpkins {
-        let center_pos = pos + 4.0;
-        let inside = (pumpkin.pos.x - 4.0..pumpkin.pos.x + 8.0 + 4.0).contains(&center_pos.x)
-            && (pumpkin.pos.y - 4.0..pumpkin.pos.y + 8.0 + 4.0).contains(&center_pos.y);
-        if inside {
-            let delta_x = pos.x - pumpkin.pos.x;
-            let delta_y = center_pos.y - pumpkin.pos.y;
-
-            if delta_y < 0.0 {
-                pos.y = pumpkin.pos.y - 4.0 - 4.0;
-                if velocity.y > 0.0 {
-                    velocity.y = 0.0;
-                    on_ground = true;
-                }
-            } else if delta_x.abs() > delta_y.abs() {
-                if delta_x < 0.0 {
-                    pos.x = pumpkin.pos.x - 4.0 - 4.0;
-                    if velocity.x > 0.0 {
-                        velocity.x = 0.0;
-                    }
-                } else {
-                    pos.x = pumpkin.pos.x + 7.0 + 4.0 - 4.0;
-                    if velocity.x < 0.0 {
-                        velocity.x = 0.0;
-                    }
-                }
-            }
+    hitbox: Hitbox,
+    pumpkins: &[Rect],
+) -> (Vec2, Contacts) {
+    let mut contacts = Contacts::default();
+    let mut pumpkins = pumpkins.to_vec();
+
+    // how far the body can go in each direction without going back into a pumpkin it was pushed out of
+    let mut min = vec2(f32::NEG_INFINITY, f32::NEG_INFINITY);
+    let mut max = vec2(f32::INFINITY, f32::INFINITY);
+    // pushing the body out of one pumpkin can push it into another, so the deepest is picked each time
+    loop {
+        let body = hitbox.at(pos);
+        let Some((index, depth)) = pumpkins
+            .iter()
+            .map(|f| penetration(body, *f))
+            .enumerate()
+            .filter(|(_, depth)| depth.min_element() > EPSILON)
+            .max_by(|a, b| a.1.min_element().total_cmp(&b.1.min_element()))
+        else {
             break;
+        };
+        let pumpkin = pumpkins.swap_remove(index);
+        let axis = if depth.y <= depth.x { 1 } else { 0 };
+        let before = body.center()[axis] < pumpkin.center()[axis];
+        if before {
+            max[axis] = max[axis].min(pumpkin.point()[axis]);
+            if velocity[axis] > 0.0 {
+                velocity[axis] = 0.0;
+            }
+        } else {
+            min[axis] = min[axis].max(pumpkin.point()[axis] + pumpkin.size()[axis]);
+            if velocity[axis] < 0.0 {
+                velocity[axis] = 0.0;
+            }
         }
+        match (axis, before) {
+            (0, true) => contacts.right = true,
+            (0, false) => contacts.left = true,
+            (_, true) => contacts.down = true,
+            (_, false) => contacts.up = true,
+        }
+        let size = hitbox.size[axis];
+        let start = if min[axis] + size > max[axis] {
+            (min[axis] + max[axis] - size) / 2.0
+        } else {
+            body.point()[axis].clamp(min[axis], max[axis] - size)
+        };
+        pos[axis] = start - hitbox.offset[axis];
+    }
+    (pos, contacts)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::LazyLock;
+
+    use super::*;
+    use crate::{assets::Pumpkin, utils::TICK};
+
+    const BOX: Hitbox = Hitbox::new(0.0, 0.0, 8.0, 8.0);
+
+    /// Tiles without any properties.
+    fn tiles<'a>(solid: &'a Layer, one_way: &'a Layer) -> Tiles<'a> {
+        static PROPERTIES: LazyLock<HashMap<i16, TileProperties>> = LazyLock::new(HashMap::new);
+        Tiles {
+            collision: solid,
+            one_way,
+            properties: &PROPERTIES,
+        }
+    }
+
+    /// A layer with a tile at each of `tiles`.
+    fn layer(tiles: impl IntoIterator<Item = (i16, i16)>) -> Layer {
+        let mut layer = Layer::default();
+        for (x, y) in tiles {
+            layer.set_tile(x, y, 1);
+        }
+        layer
+    }
+
+    /// Moves a body under gravity until it lands, returning where it landed and the highest point reached.
+    fn fall(mut pos: Vec2, mut velocity: Vec2, hitbox: Hitbox, tiles: &Tiles) -> (Vec2, f32) {
+        let mut highest = pos.y;
+        for _ in 0..600 {
+            velocity.y += PhysicsConfig::default().gravity * TICK;
+            let contacts;
+            (pos, contacts) = update_physicsbody(pos, &mut velocity, hitbox, TICK, tiles, &[]);
+            highest = highest.min(pos.y);
+            if contacts.down {
+                assert_eq!(velocity.y, 0.0);
+                return (pos, highest);
+            }
+        }
+        panic!("never landed, ended up at {pos}");
+    }
+
+    /// Moves a body at a constant velocity for a second, returning where it ended up and what it hit.
+    fn slide(mut pos: Vec2, mut velocity: Vec2, solid: &Layer) -> (Vec2, Vec2, Contacts) {
+        let mut hit = Contacts::default();
+        for _ in 0..60 {
+            let contacts;
+            (pos, contacts) = update_physicsbody(
+                pos,
+                &mut velocity,
+                BOX,
+                TICK,
+                &tiles(solid, &Layer::default()),
+                &[],
+            );
+            hit.up |= contacts.up;
+            hit.down |= contacts.down;
+            hit.left |= contacts.left;
+            hit.right |= contacts.right;
+        }
+        (pos, velocity, hit)
+    }
+
+    #[test]
+    fn lands_on_solid_tiles() {
+        let floor = layer((0..16).map(|x| (x, 10)));
+        let (pos, _) = fall(
+            vec2(40.0, 20.0),
+            Vec2::ZERO,
+            BOX,
+            &tiles(&floor, &Layer::default()),
+        );
+        assert_eq!(pos, vec2(40.0, 72.0));
+
+        // straddling two tiles
+        let (pos, _) = fall(
+            vec2(43.5, 20.0),
+            Vec2::ZERO,
+            BOX,
+            &tiles(&floor, &Layer::default()),
+        );
+        assert_eq!(pos.y, 72.0);
+    }
+
+    #[test]
+    fn bumps_head_on_solid_tiles() {
+        let ceiling = layer((0..16).map(|x| (x, 2)));
+        let (pos, velocity, hit) = slide(vec2(40.0, 60.0), vec2(0.0, -300.0), &ceiling);
+        assert_eq!(pos.y, 24.0);
+        assert_eq!(velocity.y, 0.0);
+        assert!(hit.up && !hit.down);
+    }
+
+    #[test]
+    fn jumps_up_through_one_way_platforms() {
+        let platform = layer((0..16).map(|x| (x, 10)));
+        let (pos, highest) = fall(
+            vec2(40.0, 96.0),
+            vec2(0.0, -300.0),
+            BOX,
+            &tiles(&Layer::default(), &platform),
+        );
+        assert!(highest < 72.0, "only got up to {highest}");
+        assert_eq!(pos, vec2(40.0, 72.0));
+    }
+
+    #[test]
+    fn stops_at_walls() {
+        let walls = layer((0..16).flat_map(|y| [(3, y), (10, y)]));
+
+        let (pos, velocity, hit) = slide(vec2(50.0, 40.0), vec2(100.0, 0.0), &walls);
+        assert_eq!(pos, vec2(72.0, 40.0));
+        assert_eq!(velocity.x, 0.0);
+        assert!(hit.right && !hit.left);
+
+        let (pos, velocity, hit) = slide(vec2(50.0, 40.0), vec2(-100.0, 0.0), &walls);
+        assert_eq!(pos, vec2(32.0, 40.0));
+        assert_eq!(velocity.x, 0.0);
+        assert!(hit.left && !hit.right);
+    }
+
+    #[test]
+    fn fast_bodies_dont_pass_through_tiles() {
+        let floor = layer((0..16).map(|x| (x, 10)));
+        let mut velocity = vec2(0.0, 6000.0);
+        let (pos, contacts) = update_physicsbody(
+            vec2(40.0, 8.0),
+            &mut velocity,
+            BOX,
+            TICK,
+            &tiles(&floor, &Layer::default()),
+            &[],
+        );
+        assert_eq!(pos.y, 72.0);
+        assert!(contacts.down);
+
+        let wall = layer((0..16).map(|y| (10, y)));
+        let mut velocity = vec2(6000.0, 0.0);
+        let (pos, contacts) = update_physicsbody(
+            vec2(8.0, 40.0),
+            &mut velocity,
+            BOX,
+            TICK,
+            &tiles(&wall, &Layer::default()),
+            &[],
+        );
+        assert_eq!(pos.x, 72.0);
+        assert!(contacts.right);
+    }
+
+    #[test]
+    fn limits_how_far_bodies_move() {
+        let floor = layer((0..16).map(|x| (x, 10)));
+        let empty = Layer::default();
+        let tiles = tiles(&floor, &empty);
+        let mut velocity = vec2(f32::INFINITY, 0.0);
+        let (pos, _) = update_physicsbody(vec2(8.0, 8.0), &mut velocity, BOX, TICK, &tiles, &[]);
+        assert_eq!(pos, vec2(8.0, 8.0));
+        assert_eq!(velocity, Vec2::ZERO);
+
+        let mut velocity = vec2(0.0, f32::MAX);
+        let (pos, contacts) =
+            update_physicsbody(vec2(40.0, 8.0), &mut velocity, BOX, TICK, &tiles, &[]);
+        assert_eq!(pos.y, 72.0);
+        assert!(contacts.down);
+    }
+
+    #[test]
+    fn uses_the_hitbox() {
+        let floor = layer((0..16).map(|x| (x, 10)));
+        let tall = Hitbox::new(2.0, 0.0, 4.0, 16.0);
+        let (pos, _) = fall(
+            vec2(40.0, 20.0),
+            Vec2::ZERO,
+            tall,
+            &tiles(&floor, &Layer::default()),
+        );
+        assert_eq!(pos.y, 64.0);
+
+        // narrow enough to fit down a one tile gap
+        let gap = layer(
+            (0..16)
+                .filter(|x| *x != 5)
+                .map(|x| (x, 10))
+                .chain((0..16).map(|x| (x, 14))),
+        );
+        let (pos, _) = fall(
+            vec2(38.0, 20.0),
+            Vec2::ZERO,
+            tall,
+            &tiles(&gap, &Layer::default()),
+        );
+        assert_eq!(pos.y, 96.0);
+    }
+
+    fn pumpkin(x: f32, y: f32) -> Rect {
+        Pumpkin::HITBOX.at(vec2(x, y))
+    }
+
+    #[test]
+    fn stands_on_pumpkins() {
+        let pumpkins = [pumpkin(40.0, 40.0)];
+        let mut velocity = vec2(0.0, 50.0);
+        let (pos, contacts) =
+            collide_with_pumpkins(vec2(42.0, 33.0), &mut velocity, BOX, &pumpkins);
+        assert_eq!(pos, vec2(42.0, 32.0));
+        assert!(contacts.down);
+        assert_eq!(velocity.y, 0.0);
+
+        // walking into one from the side
+        let mut velocity = vec2(50.0, 0.0);
+        let (pos, contacts) =
+            collide_with_pumpkins(vec2(34.0, 40.0), &mut velocity, BOX, &pumpkins);
+        assert_eq!(pos, vec2(32.0, 40.0));
+        assert_eq!(
+            contacts,
+            Contacts {
+                right: true,
+                ..Default::default()
+            }
+        );
+        assert_eq!(velocity.x, 0.0);
+    }
+
+    #[test]
+    fn bumps_head_on_pumpkins() {
+        let pumpkins = [pumpkin(40.0, 40.0)];
+        let mut velocity = vec2(10.0, -100.0);
+        let (pos, contacts) =
+            collide_with_pumpkins(vec2(41.0, 46.0), &mut velocity, BOX, &pumpkins);
+        assert_eq!(pos, vec2(41.0, 48.0));
+        assert!(contacts.up);
+        assert_eq!(velocity, vec2(10.0, 0.0));
+    }
+
+    #[test]
+    fn stands_across_pumpkins() {
+        // sunk into two pumpkins next to each other, which shouldn't count as walking into the second
+        let pumpkins = [pumpkin(40.0, 40.0), pumpkin(48.0, 40.0)];
+        let mut velocity = vec2(50.0, 50.0);
+        let (pos, contacts) =
+            collide_with_pumpkins(vec2(45.0, 33.0), &mut velocity, BOX, &pumpkins);
+        assert_eq!(pos, vec2(45.0, 32.0));
+        assert_eq!(
+            contacts,
+            Contacts {
+                down: true,
+                ..Default::default()
+            }
+        );
+        assert_eq!(velocity, vec2(50.0, 0.0));
+    }
+
+    #[test]
+    fn squeezed_between_pumpkins() {
+        let pumpkins = [pumpkin(30.0, 40.0), pumpkin(44.0, 40.0)];
+        for x in [36.0, 37.0, 38.0] {
+            let mut velocity = vec2(50.0, 0.0);
+            let (pos, contacts) =
+                collide_with_pumpkins(vec2(x, 40.0), &mut velocity, BOX, &pumpkins);
+            assert_eq!(pos, vec2(37.0, 40.0));
+            assert!(contacts.left && contacts.right);
+            assert_eq!(velocity.x, 0.0);
+        }
+
+        // with enough room, it just gets pushed out
+        let pumpkins = [pumpkin(30.0, 40.0), pumpkin(47.0, 40.0)];
+        let mut velocity = Vec2::ZERO;
+        let (pos, _) = collide_with_pumpkins(vec2(37.0, 40.0), &mut velocity, BOX, &pumpkins);
+        assert_eq!(pos, vec2(38.0, 40.0));
+    }
+
+    #[test]
+    fn blocked_by_bodies() {
+        let pumpkins = [Pumpkin::HITBOX.at(vec2(60.0, 40.0))];
+        let mut velocity = vec2(100.0, 0.0);
+        let mut pos = vec2(40.0, 40.0);
+        for _ in 0..30 {
+            (pos, _) = update_physicsbody(
+                pos,
+                &mut velocity,
+                BOX,
+                TICK,
+                &tiles(&Layer::default(), &Layer::default()),
+                &pumpkins,
+            );
+        }
+        assert_eq!(pos, vec2(52.0, 40.0));
+        assert_eq!(velocity.x, 0.0);
+    }
+
+    /// Owns everything `Tiles` borrows, for tests that need tile properties.
+    struct Level {
+        collision: Layer,
+        properties: HashMap<i16, TileProperties>,
+    }
+    impl Level {
+        fn tiles(&self) -> Tiles<'_> {
+            static ONE_WAY: LazyLock<Layer> = LazyLock::new(Layer::default);
+            Tiles {
+                collision: &self.collision,
+                one_way: &ONE_WAY,
+                properties: &self.properties,
+            }
+        }
+    }
+
+    /// Floor along row 10, raised up a tile from column 7 onwards, with a slope in between.
+    fn ramp(shape: TileShape) -> Level {
+        let mut collision = layer((0..16).map(|x| (x, 10)).chain((7..16).map(|x| (x, 9))));
+        collision.set_tile(6, 9, 2);
+        let properties = TileProperties {
+            shape,
+            ..Default::default()
+        };
+        Level {
+            collision,
+            properties: HashMap::from([(2, properties)]),
+        }
+    }
+
+    /// Walks a body on the ground for a second, checking it stays on the ground.
+    fn walk(mut pos: Vec2, speed: f32, tiles: &Tiles) -> Vec2 {
+        let mut velocity = Vec2::ZERO;
+        for _ in 0..60 {
+            velocity.x = speed;
+            velocity.y += PhysicsConfig::default().gravity * TICK;
+            let contacts;
+            (pos, contacts) = update_physicsbody(pos, &mut velocity, BOX, TICK, tiles, &[]);
+            assert!(contacts.down, "left the ground at {pos}");
+        }
+        pos
+    }
+
+    #[test]
+    fn walks_along_slopes() {
+        let level = ramp(TileShape {
+            left: 0.0,
+            right: 8.0,
+        });
+        let tiles = level.tiles();
+        let pos = walk(vec2(20.0, 72.0), 60.0, &tiles);
+        assert_eq!(pos, vec2(80.0, 64.0));
+        let pos = walk(pos, -60.0, &tiles);
+        assert_eq!(pos, vec2(20.0, 72.0));
+
+        // halfway up, standing on the slope under the middle of the body
+        let (pos, _) = fall(vec2(48.0, 40.0), Vec2::ZERO, BOX, &tiles);
+        assert_eq!(pos, vec2(48.0, 68.0));
+    }
+
+    #[test]
+    fn half_blocks_are_walls() {
+        let level = ramp(TileShape {
+            left: 4.0,
+            right: 4.0,
+        });
+        let tiles = level.tiles();
+        let pos = walk(vec2(20.0, 72.0), 60.0, &tiles);
+        assert_eq!(pos, vec2(40.0, 72.0));
+
+        let (pos, _) = fall(vec2(48.0, 40.0), Vec2::ZERO, BOX, &tiles);
+        assert_eq!(pos, vec2(48.0, 68.0));
+    }
+
+    #[test]
+    fn pumpkins_roll_down_slopes() {
+        let level = ramp(TileShape {
+            left: 0.0,
+            right: 8.0,
+        });
+        let tiles = level.tiles();
+        let mut pumpkin = Pumpkin::new(vec2(48.0, 60.0));
+        pumpkin.on_ground = false;
+        for _ in 0..60 {
+            pumpkin.update(&PhysicsConfig::default(), TICK, &tiles, &[]);
+        }
+        assert!(pumpkin.on_ground);
+        // all the way down, off the slope
+        assert!(
+            pumpkin.pos.x + 4.0 < 48.0,
+            "only rolled to {}",
+            pumpkin.pos.x
+        );
+        assert_eq!(pumpkin.pos.y, 72.0);
+    }
+
+    /// Floor along row 10 made of tile 2, with `properties`.
+    fn flat(properties: TileProperties) -> Level {
+        let mut collision = Layer::default();
+        for x in 0..16 {
+            collision.set_tile(x, 10, 2);
+        }
+        Level {
+            collision,
+            properties: HashMap::from([(2, properties)]),
+        }
+    }
+
+    #[test]
+    fn properties_override_layers() {
+        let level = flat(TileProperties {
+            one_way: Some(true),
+            ..Default::default()
+        });
+        let tiles = level.tiles();
+        // jumps up through it
+        let (pos, highest) = fall(vec2(40.0, 88.0), vec2(0.0, -300.0), BOX, &tiles);
+        assert!(highest < 72.0);
+        assert_eq!(pos, vec2(40.0, 72.0));
+
+        let mut level = flat(TileProperties {
+            solid: Some(false),
+            ..Default::default()
+        });
+        // with a normal floor further down
+        for x in 0..16 {
+            level.collision.set_tile(x, 14, 1);
+        }
+        let (pos, _) = fall(vec2(40.0, 40.0), Vec2::ZERO, BOX, &level.tiles());
+        assert_eq!(pos, vec2(40.0, 104.0));
+    }
+
+    #[test]
+    fn bounces_on_bouncy_tiles() {
+        let level = flat(TileProperties {
+            bouncy: 0.5,
+            ..Default::default()
+        });
+        let tiles = level.tiles();
+        let mut velocity = vec2(0.0, 200.0);
+        let (_, contacts) =
+            update_physicsbody(vec2(40.0, 70.0), &mut velocity, BOX, TICK, &tiles, &[]);
+        assert!(contacts.down);
+        assert_eq!(velocity.y, -100.0);
+
+        // slow landings don't bounce, so bodies can come to rest
+        let mut velocity = vec2(0.0, 40.0);
+        update_physicsbody(vec2(40.0, 72.0), &mut velocity, BOX, TICK, &tiles, &[]);
+        assert_eq!(velocity.y, 0.0);
+    }
+
+    #[test]
+    fn slides_further_on_slippery_tiles() {
+        let slide = |friction| {
+            let level = flat(TileProperties {
+                friction,
+                ..Default::default()
+            });
+            let tiles = level.tiles();
+            let mut pumpkin = Pumpkin::new(vec2(8.0, 72.0));
+            pumpkin.velocity.x = 60.0;
+            for _ in 0..60 {
+                pumpkin.update(&PhysicsConfig::default(), TICK, &tiles, &[]);
+            }
+            pumpkin.pos.x
+        };
+        assert!(slide(0.2) > slide(1.0) + 8.0);
+    }
+
+    #[test]
+    fn finds_touching_tiles() {
+        let level = flat(TileProperties {
+            hazard: true,
+            ..Default::default()
+        });
+        let tiles = level.tiles();
+        let touching = |y| tiles.touching(BOX.at(vec2(40.0, y))).any(|f| f.hazard);
+        assert!(touching(73.0));
+        assert!(!touching(72.0));
+    }
+
+    #[test]
+    fn physics_file_matches_defaults() {
+        let text = crate::asset_source::AssetSource::default()
+            .read_string(PhysicsConfig::PATH)
+            .unwrap();
+        assert_eq!(
+            PhysicsConfig::from_toml(&text).unwrap(),
+            PhysicsConfig::default()
+        );
+        // missing constants keep their defaults, and typos aren't ignored
+        let config = PhysicsConfig::from_toml("gravity = 10.0").unwrap();
+        assert_eq!(config.gravity, 10.0);
+        assert_eq!(config.jump_impulse, 180.0);
+        assert!(PhysicsConfig::from_toml("gravty = 10.0").is_err());
+        assert!(PhysicsConfig::from_toml("gravity = nan").is_err());
+        assert!(PhysicsConfig::from_toml("gravity = inf").is_err());
+    }
+
+    #[test]
+    fn sets_constants_by_name() {
+        let mut config = PhysicsConfig::default();
+        assert!(config.names().contains(&"jump-impulse".to_string()));
+        config.set("jump-impulse", 200.0).unwrap();
+        assert_eq!(config.jump_impulse, 200.0);
+        assert_eq!(config.get("jump-impulse"), Some(200.0));
+        assert!(config.set("jump", 200.0).is_err());
+        assert!(config.set("gravity", f32::NAN).is_err());
+        assert!(config.set("gravity", f32::INFINITY).is_err());
+        assert_eq!(config.gravity, 900.0);
+        assert_eq!(config.get("jump"), None);
     }
-    (pos, on_ground)
 }
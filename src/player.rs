@@ -2,80 +2,118 @@ use macroquad::prelude::*;
 
 use crate::{
     assets::*,
-    physics::{collide_with_pumpkins, update_physicsbody},
-    utils::*,
+    input::{Action, TickInput},
+    physics::{Hitbox, PhysicsConfig, collide_with_pumpkins, update_physicsbody},
 };
 
 pub struct Player {
     pub pos: Vec2,
     pub camera_pos: Vec2,
+    /// Positions at the start of the last tick, for interpolating between ticks.
+    last_pos: Vec2,
+    last_camera_pos: Vec2,
     pub velocity: Vec2,
     pub anim_frame: f32,
     pub facing_right: bool,
     pub on_ground: bool,
+    /// Friction of the ground the player is standing on, relative to normal ground.
+    pub ground_friction: f32,
+    /// On a ladder or vine, where the player moves up and down without falling.
+    pub climbing: bool,
+    /// Seconds since the player jumped, or 0 once the jump can't be cut short anymore.
     pub jump_frames: f32,
+    /// Seconds left in which the player can still jump after walking off a ledge.
+    pub coyote_time: f32,
+    /// Seconds left in which a jump pressed too early still happens, once the player can jump.
+    pub jump_buffer: f32,
 
-    pub poi_location: Vec2,
+    pub poi_location: Option<Vec2>,
 
     pub carrying: Option<Pumpkin>,
-
-    body_animation: Animation,
-    carry_animation: Animation,
-    walk_animation: Animation,
-    idle_animation: Animation,
 }
-impl Player {
-    pub fn new() -> Self {
+impl Default for Player {
+    fn default() -> Self {
         Self {
             carrying: None,
             pos: Vec2::ZERO,
             camera_pos: Vec2::ZERO,
+            last_pos: Vec2::ZERO,
+            last_camera_pos: Vec2::ZERO,
             velocity: Vec2::ZERO,
-            poi_location: Vec2::ZERO,
+            poi_location: None,
             anim_frame: 0.0,
             jump_frames: 0.0,
+            coyote_time: 0.0,
+            jump_buffer: 0.0,
             facing_right: true,
             on_ground: false,
-            body_animation: Animation::from_file(include_bytes!("../assets/player_body.ase")),
-            carry_animation: Animation::from_file(include_bytes!("../assets/player_carry.ase")),
-            walk_animation: Animation::from_file(include_bytes!("../assets/player_walk.ase")),
-            idle_animation: Animation::from_file(include_bytes!("../assets/player_idle.ase")),
+            ground_friction: 1.0,
+            climbing: false,
         }
     }
-    pub fn update(&mut self, world: &mut World, delta_time: f32) {
+}
+impl Player {
+    /// The top row of the sprite is empty. A carried pumpkin isn't part of the hitbox, so it can
+    /// poke into ceilings.
+    pub const HITBOX: Hitbox = Hitbox::new(0.0, 1.0, 8.0, 7.0);
+    /// Makes the player be drawn at their current position, instead of moving there from their last one.
+    pub fn snap(&mut self) {
+        self.last_pos = self.pos;
+        self.last_camera_pos = self.camera_pos;
+    }
+    /// Position between the last tick and the current one.
+    pub fn interpolated_pos(&self, alpha: f32) -> Vec2 {
+        self.last_pos.lerp(self.pos, alpha)
+    }
+    /// Camera position between the last tick and the current one.
+    pub fn interpolated_camera_pos(&self, alpha: f32) -> Vec2 {
+        self.last_camera_pos.lerp(self.camera_pos, alpha)
+    }
+    pub fn update(
+        &mut self,
+        world: &mut World,
+        input: &TickInput,
+        physics: &PhysicsConfig,
+        delta_time: f32,
+    ) {
+        self.snap();
         self.anim_frame += delta_time * 1000.0;
 
-        // only allow noclip on debug builds
-        #[cfg(debug_assertions)]
-        let noclip = is_key_down(KeyCode::LeftShift);
-        #[cfg(not(debug_assertions))]
-        let noclip = { false };
+        let noclip = input.noclip;
 
         let can_move = true;
 
         let mut forces = Vec2::ZERO;
 
-        if !noclip {
-            forces.y += GRAVITY;
+        let can_climb = world
+            .tiles()
+            .touching(Self::HITBOX.at(self.pos))
+            .any(|f| f.climbable);
+        if !can_climb || noclip {
+            self.climbing = false;
+        } else if input.down(Action::Up) || input.down(Action::Down) {
+            self.climbing = true;
+        }
+
+        if !noclip && !self.climbing {
+            forces.y += physics.gravity;
         }
 
         if can_move {
-            if is_key_down(KeyCode::A) {
-                forces.x -= 1.0 * 3600.0;
+            if input.down(Action::MoveLeft) {
+                forces.x -= physics.run_force;
                 self.facing_right = false;
             }
-            if is_key_down(KeyCode::D) {
-                forces.x += 1.0 * 3600.0;
+            if input.down(Action::MoveRight) {
+                forces.x += physics.run_force;
                 self.facing_right = true;
             }
         }
-        if !noclip && !self.on_ground {
-            if forces.x * self.velocity.x > 0.0 {
-                forces.x *= 0.02;
-            }
+        if !noclip && !self.on_ground && !self.climbing && forces.x * self.velocity.x > 0.0 {
+            forces.x *= physics.air_control;
         }
 
-        let interacted = is_key_pressed(KeyCode::E) || is_mouse_button_pressed(MouseButton::Left);
+        let interacted = input.pressed(Action::Interact);
 
         if interacted && self.carrying.is_none() {
             if self.velocity.y == 0.0 {
@@ -88,47 +126,67 @@ impl Player {
                     self.carrying = Some(pumpkin);
                 }
             }
-        } else if interacted && self.pos.distance(self.poi_location) > 16.0 {
+        } else if interacted
+            && self
+                .poi_location
+                .is_none_or(|f| self.pos.distance(f) > 16.0)
+        {
             let mut pumpkin = self.carrying.take().unwrap();
-            let input_dir = get_input_axis();
+            // a stick that's slightly off straight still throws straight
+            let input_dir = input.direction();
             let mut dir = Vec2::ZERO;
             if input_dir.y < 0.0 && input_dir.x != 0.0 {
-                dir = input_dir;
+                dir = input_dir.normalize();
             } else if input_dir.y == 0.0 {
                 dir.x = if self.facing_right { 1.0 } else { -1.0 };
             } else if input_dir.y > 0.0 && input_dir.x != 0.0 {
                 dir.x = input_dir.x;
             }
-            pumpkin.velocity = dir * 3.0 * 60.0;
+            pumpkin.velocity = dir * physics.throw_speed;
+            pumpkin.through_player = true;
             world.pumpkins.push(pumpkin);
         }
 
-        if self.on_ground {
+        if self.on_ground || self.climbing {
             self.jump_frames = 0.0;
+            self.coyote_time = physics.coyote_time;
+        } else {
+            self.coyote_time = (self.coyote_time - delta_time).max(0.0);
         }
-        if can_move
-            && is_key_down(KeyCode::Space)
-            && (self.on_ground || (self.jump_frames > 0.0 && self.jump_frames < 0.5))
-        {
-            if self.jump_frames == 0.0 {
-                self.velocity.y -= 3.0 * 60.0;
-            } else {
-                //self.velocity.y -= 60.0 * 10.0 * delta_time;
-                //forces.y -= 60.0 * 10.0;
-            }
+        if input.pressed(Action::Jump) {
+            self.jump_buffer = physics.jump_buffer;
+        } else {
+            self.jump_buffer = (self.jump_buffer - delta_time).max(0.0);
+        }
+        if can_move && self.jump_buffer > 0.0 && (self.coyote_time > 0.0 || self.climbing) {
+            self.climbing = false;
+            self.jump_buffer = 0.0;
+            self.coyote_time = 0.0;
+            self.velocity.y = -physics.jump_impulse;
+            self.jump_frames = delta_time;
+        } else if self.jump_frames > 0.0 {
             self.jump_frames += delta_time;
+            // letting go early makes for a lower jump
+            if !input.down(Action::Jump) && self.velocity.y < 0.0 {
+                self.velocity.y *= physics.jump_cut;
+                self.jump_frames = 0.0;
+            } else if self.jump_frames >= physics.jump_window {
+                self.jump_frames = 0.0;
+            }
         }
 
         if noclip {
-            if is_key_down(KeyCode::W) {
-                forces.y -= 1.0 * 3600.0;
+            if input.down(Action::Up) {
+                forces.y -= physics.run_force;
             }
-            if is_key_down(KeyCode::S) {
-                forces.y += 1.0 * 3600.0;
+            if input.down(Action::Down) {
+                forces.y += physics.run_force;
             }
             self.velocity += forces * 3.0 * delta_time;
 
-            self.velocity = self.velocity.lerp(Vec2::ZERO, GROUND_FRICTION * delta_time);
+            self.velocity = self
+                .velocity
+                .lerp(Vec2::ZERO, physics.ground_friction * delta_time);
 
             self.pos += self.velocity * delta_time;
             self.camera_pos = self.pos.floor();
@@ -136,30 +194,58 @@ impl Player {
         }
 
         self.velocity += forces * delta_time;
-        let on_pumpkin;
-        self.velocity.x = self.velocity.x.clamp(-MAX_VELOCITY, MAX_VELOCITY);
+        self.velocity.x = self
+            .velocity
+            .x
+            .clamp(-physics.max_velocity, physics.max_velocity);
         self.velocity.x -= self.velocity.x
             * delta_time
-            * if self.on_ground {
+            * if self.on_ground || self.climbing {
+                let friction = if self.climbing {
+                    1.0
+                } else {
+                    self.ground_friction
+                };
                 if forces.x == 0.0 {
-                    GROUND_FRICTION * 3.0
+                    physics.ground_friction * 3.0 * friction
                 } else {
-                    GROUND_FRICTION
+                    physics.ground_friction * friction
                 }
             } else {
-                AIR_DRAG
+                physics.air_drag
             };
+        if self.climbing {
+            let climb = input.down(Action::Down) as i8 - input.down(Action::Up) as i8;
+            self.velocity.y = climb as f32 * physics.climb_speed;
+        }
 
-        (self.pos, on_pumpkin) =
-            collide_with_pumpkins(self.pos, &mut self.velocity, &world.pumpkins);
-        (self.pos, self.on_ground) = update_physicsbody(
+        let hitbox = Self::HITBOX.at(self.pos);
+        for pumpkin in &mut world.pumpkins {
+            pumpkin.through_player &= Pumpkin::HITBOX.at(pumpkin.pos).overlaps(&hitbox);
+        }
+        let pumpkins: Vec<Rect> = world
+            .pumpkins
+            .iter()
+            .filter(|f| !f.through_player)
+            .map(|f| Pumpkin::HITBOX.at(f.pos))
+            .collect();
+        let (in_pumpkin, on_tiles);
+        (self.pos, in_pumpkin) =
+            collide_with_pumpkins(self.pos, &mut self.velocity, Self::HITBOX, &pumpkins);
+        (self.pos, on_tiles) = update_physicsbody(
             self.pos,
             &mut self.velocity,
+            Self::HITBOX,
             delta_time,
-            &world.collision,
-            &world.one_way_collision,
+            &world.tiles(),
+            &pumpkins,
         );
-        self.on_ground |= on_pumpkin;
+        self.on_ground = on_tiles.down || in_pumpkin.down;
+        self.ground_friction = if on_tiles.down {
+            on_tiles.friction()
+        } else {
+            1.0
+        };
 
         if self.velocity.x.abs() <= 2.0 && forces.x == 0.0 {
             self.velocity.x = 0.0;
@@ -173,19 +259,22 @@ impl Player {
         }
 
         if let Some(pumpkin) = &mut self.carrying {
+            pumpkin.last_pos = pumpkin.pos;
             pumpkin.pos = self.pos + vec2(0.0, -7.0);
         }
     }
-    pub fn draw(&self, assets: &Assets) {
+    /// Draws the player `alpha` of the way from the last tick to the current one.
+    pub fn draw(&self, assets: &Assets, alpha: f32) {
+        let pos = self.interpolated_pos(alpha).floor();
         let torso_animation = if self.carrying.is_some() {
-            &self.carry_animation
+            &assets.player_carry
         } else {
-            &self.body_animation
+            &assets.player_body
         };
         draw_texture_ex(
             torso_animation.get_at_time(self.anim_frame as u32),
-            self.pos.floor().x,
-            self.pos.floor().y,
+            pos.x,
+            pos.y,
             WHITE,
             DrawTextureParams {
                 flip_x: !self.facing_right,
@@ -193,14 +282,14 @@ impl Player {
             },
         );
         let legs_animation = if self.velocity.length() > 0.0 {
-            &self.walk_animation
+            &assets.player_walk
         } else {
-            &self.idle_animation
+            &assets.player_idle
         };
         draw_texture_ex(
             legs_animation.get_at_time(self.anim_frame as u32),
-            self.pos.floor().x,
-            self.pos.floor().y,
+            pos.x,
+            pos.y,
             WHITE,
             DrawTextureParams {
                 flip_x: !self.facing_right,
@@ -208,7 +297,7 @@ impl Player {
             },
         );
         if let Some(pumpkin) = &self.carrying {
-            pumpkin.draw(assets, &vec2(0.0, 0.0), false);
+            pumpkin.draw(assets, &vec2(0.0, 0.0), false, alpha);
         }
     }
 }
@@ -0,0 +1,200 @@
+//! Drawing the tile layers of a world, pre-rendered a chunk at a time.
+
+use std::collections::HashMap;
+
+use macroquad::prelude::*;
+
+use crate::assets::{Assets, World, WorldLayer};
+
+/// Size of a chunk, in pixels.
+pub const CHUNK_SIZE: f32 = 16.0 * 8.0;
+/// Most chunk textures made, which caps the memory used at 64KiB each.
+/// More than fit on screen normally, which is 3x3 chunks. Chunks past this when zoomed out are
+/// drawn tile by tile instead.
+const MAX_CHUNKS: usize = 32;
+
+/// Draws the tiles of a world from textures of the chunks around the camera, so that neither
+/// each tile has to be drawn every frame, nor the whole world kept in one huge texture.
+/// Animated tiles are drawn every frame, on top of the texture of the layers up to theirs.
+#[derive(Default)]
+pub struct WorldRenderer {
+    /// Pre-rendered chunks, by position in tiles. Chunks without any tiles aren't kept, since
+    /// finding that out again is as quick as drawing them.
+    chunks: HashMap<(i16, i16), RenderedChunk>,
+    /// Textures of chunks that were thrown away, to reuse for new ones.
+    unused: Vec<RenderTarget>,
+    /// Number of textures made, whether in use or not.
+    made: usize,
+    frame: u32,
+}
+
+struct RenderedChunk {
+    /// A texture for each band of layers, in the order they're drawn.
+    bands: Vec<(&'static [WorldLayer], RenderTarget)>,
+    /// Last frame the chunk was drawn on.
+    last_drawn: u32,
+}
+impl RenderedChunk {
+    fn targets(self) -> impl Iterator<Item = RenderTarget> {
+        self.bands.into_iter().map(|(_, target)| target)
+    }
+}
+
+impl WorldRenderer {
+    /// Throws away every pre-rendered chunk, for when the world or tileset changes.
+    pub fn clear(&mut self) {
+        let targets = self.chunks.drain().flat_map(|(_, f)| f.targets());
+        self.unused.extend(targets);
+    }
+    /// Throws away the pre-rendered chunks overlapping `region`, so they're drawn again with
+    /// whatever changed there.
+    pub fn redraw(&mut self, region: Rect) {
+        for key in chunks_in(region) {
+            if let Some(chunk) = self.chunks.remove(&key) {
+                self.unused.extend(chunk.targets());
+            }
+        }
+    }
+    /// Pre-renders the chunks within `view` that aren't already.
+    /// Has to be called before setting the camera the world is drawn with.
+    pub fn update(&mut self, assets: &Assets, world: &World, view: Rect) {
+        self.frame += 1;
+        for key in chunks_in(view) {
+            if self.chunks.contains_key(&key) {
+                continue;
+            }
+            let region = chunk_rect(key);
+            if !world.has_tiles_in(region) {
+                continue;
+            }
+            let bands = bands(|layer| world.has_animated_in(layer, region));
+            let mut targets = Vec::new();
+            for _ in &bands {
+                targets.extend(self.take_target(view));
+            }
+            // left to `draw` once every texture is in view
+            if targets.len() < bands.len() {
+                self.unused.extend(targets);
+                continue;
+            }
+            let bands: Vec<_> = bands.into_iter().zip(targets).collect();
+            for (layers, target) in &bands {
+                set_camera(&Camera2D {
+                    render_target: Some(target.clone()),
+                    zoom: Vec2::splat(2.0 / CHUNK_SIZE),
+                    target: region.center(),
+                    ..Default::default()
+                });
+                clear_background(BLACK.with_alpha(0.0));
+                for layer in *layers {
+                    world.draw_layer(assets, *layer, region);
+                }
+            }
+            let rendered = RenderedChunk {
+                bands,
+                last_drawn: self.frame,
+            };
+            self.chunks.insert(key, rendered);
+        }
+    }
+    /// A texture for a new chunk, from one that was thrown away or the least recently drawn chunk
+    /// outside of `view` once there are too many. `None` if all `MAX_CHUNKS` are in view.
+    fn take_target(&mut self, view: Rect) -> Option<RenderTarget> {
+        if let Some(target) = self.unused.pop() {
+            return Some(target);
+        }
+        if self.made < MAX_CHUNKS {
+            self.made += 1;
+            let target = render_target(CHUNK_SIZE as u32, CHUNK_SIZE as u32);
+            target.texture.set_filter(FilterMode::Nearest);
+            return Some(target);
+        }
+        let visible: Vec<_> = chunks_in(view).collect();
+        let oldest = self
+            .chunks
+            .iter()
+            .filter(|(key, _)| !visible.contains(key))
+            .min_by_key(|(_, f)| f.last_drawn)
+            .map(|(key, _)| *key)?;
+        let chunk = self.chunks.remove(&oldest)?;
+        self.unused.extend(chunk.targets());
+        self.unused.pop()
+    }
+    /// Draws the world's tiles within `view`, which should have been passed to `update` first.
+    /// Animated tiles are drawn as they are at `time`, in milliseconds.
+    pub fn draw(&mut self, assets: &Assets, world: &World, view: Rect, time: u32) {
+        for key in chunks_in(view) {
+            match self.chunks.get_mut(&key) {
+                Some(chunk) => {
+                    chunk.last_drawn = self.frame;
+                    let region = chunk_rect(key);
+                    for (layers, target) in &chunk.bands {
+                        draw_texture(&target.texture, region.x, region.y, WHITE);
+                        let last = *layers.last().expect("bands are never empty");
+                        world.draw_animated(assets, last, region, time);
+                    }
+                }
+                // empty, or too many chunks in view to pre-render them all
+                None => world.draw_layers(assets, chunk_rect(key), time),
+            }
+        }
+    }
+}
+
+/// Splits the layers into bands that are each pre-rendered into one texture, after every layer
+/// that's `animated`, so its animated tiles can be drawn over it and under the layers above.
+fn bands(animated: impl Fn(WorldLayer) -> bool) -> Vec<&'static [WorldLayer]> {
+    let mut bands = Vec::new();
+    let mut rest: &'static [WorldLayer] = &WorldLayer::ALL;
+    while let Some(end) = rest.iter().position(|f| animated(*f)) {
+        let (band, after) = rest.split_at(end + 1);
+        bands.push(band);
+        rest = after;
+    }
+    if !rest.is_empty() {
+        bands.push(rest);
+    }
+    bands
+}
+
+/// Positions, in tiles, of the chunks overlapping `view`.
+fn chunks_in(view: Rect) -> impl Iterator<Item = (i16, i16)> {
+    let min = (view.point() / CHUNK_SIZE).floor();
+    let max = ((view.point() + view.size()) / CHUNK_SIZE).ceil();
+    let (min, max) = (min.as_ivec2(), max.as_ivec2());
+    (min.y..max.y).flat_map(move |y| (min.x..max.x).map(move |x| (x as i16 * 16, y as i16 * 16)))
+}
+
+/// Area covered by the chunk at `key`, in pixels.
+fn chunk_rect((x, y): (i16, i16)) -> Rect {
+    Rect::new(x as f32 * 8.0, y as f32 * 8.0, CHUNK_SIZE, CHUNK_SIZE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_chunks_in_view() {
+        let chunks: Vec<_> = chunks_in(Rect::new(-10.0, 0.0, 256.0, 144.0)).collect();
+        assert_eq!(
+            chunks,
+            [(-16, 0), (0, 0), (16, 0), (-16, 16), (0, 16), (16, 16)]
+        );
+        // only touching a chunk's edge doesn't count
+        let chunks: Vec<_> = chunks_in(Rect::new(0.0, 0.0, 128.0, 128.0)).collect();
+        assert_eq!(chunks, [(0, 0)]);
+    }
+
+    #[test]
+    fn splits_bands_at_animated_layers() {
+        use WorldLayer::*;
+        assert_eq!(bands(|_| false), [&WorldLayer::ALL[..]]);
+        assert_eq!(
+            bands(|f| f == Collision),
+            [&[Background, Collision][..], &[Details, OneWay]]
+        );
+        assert_eq!(bands(|f| f == OneWay), [&WorldLayer::ALL[..]]);
+        assert_eq!(bands(|_| true).len(), 4);
+    }
+}
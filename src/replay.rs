@@ -0,0 +1,101 @@
+//! Recording the input of every tick, so a run can be played back exactly.
+//!
+//! Replays are stored as TOML, starting from a save of the state the recording began in, and the
+//! physics constants it was recorded with.
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{input::TickInput, physics::PhysicsConfig, save::SaveData, storage};
+
+/// Version written to new replays. Replays from newer versions of the game are rejected.
+pub const REPLAY_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+pub struct Replay {
+    pub version: u32,
+    /// State the recording started from.
+    pub start: SaveData,
+    /// Constants the recording was made with, which can differ from the ones in `physics.toml`.
+    pub physics: PhysicsConfig,
+    /// Input of every tick. Consecutive ticks with the same input are stored once, with their count.
+    inputs: Vec<(u32, TickInput)>,
+}
+impl Replay {
+    pub fn new(start: SaveData, physics: PhysicsConfig) -> Self {
+        Self {
+            version: REPLAY_VERSION,
+            start,
+            physics,
+            inputs: Vec::new(),
+        }
+    }
+    pub fn record(&mut self, input: TickInput) {
+        match self.inputs.last_mut() {
+            Some((count, last)) if *last == input => *count += 1,
+            _ => self.inputs.push((1, input)),
+        }
+    }
+    /// Input of every recorded tick, in order.
+    pub fn inputs(&self) -> impl Iterator<Item = TickInput> + '_ {
+        self.inputs
+            .iter()
+            .flat_map(|(count, input)| std::iter::repeat_n(*input, *count as usize))
+    }
+    pub fn ticks(&self) -> u32 {
+        self.inputs.iter().map(|(count, _)| count).sum()
+    }
+    pub fn to_toml(&self) -> Result<String, ReplayError> {
+        toml::to_string(self).map_err(|err| ReplayError::Invalid(err.to_string()))
+    }
+    pub fn from_toml(text: &str) -> Result<Self, ReplayError> {
+        // check the version on its own first, since newer replays might not parse at all
+        #[derive(Deserialize)]
+        struct Header {
+            version: u32,
+        }
+        let header: Header =
+            toml::from_str(text).map_err(|err| ReplayError::Invalid(err.to_string()))?;
+        if header.version > REPLAY_VERSION {
+            return Err(ReplayError::UnsupportedVersion(header.version));
+        }
+        toml::from_str(text).map_err(|err| ReplayError::Invalid(err.to_string()))
+    }
+}
+
+#[derive(Debug)]
+pub enum ReplayError {
+    NoReplay,
+    Storage(String),
+    Invalid(String),
+    UnsupportedVersion(u32),
+    Level(String),
+}
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::NoReplay => write!(f, "no replay found"),
+            ReplayError::Storage(err) => write!(f, "couldn't access replay: {err}"),
+            ReplayError::Invalid(err) => write!(f, "replay is corrupted: {err}"),
+            ReplayError::UnsupportedVersion(version) => write!(
+                f,
+                "replay is from a newer version of the game (version {version}, expected {REPLAY_VERSION} or older)"
+            ),
+            ReplayError::Level(err) => write!(f, "couldn't load replay level: {err}"),
+        }
+    }
+}
+impl std::error::Error for ReplayError {}
+
+const REPLAY_FILE: &str = "replay.toml";
+
+pub fn write(replay: &Replay) -> Result<(), ReplayError> {
+    storage::write(REPLAY_FILE, &replay.to_toml()?).map_err(ReplayError::Storage)
+}
+pub fn read() -> Result<Replay, ReplayError> {
+    let text = storage::read(REPLAY_FILE)
+        .map_err(ReplayError::Storage)?
+        .ok_or(ReplayError::NoReplay)?;
+    Replay::from_toml(&text)
+}
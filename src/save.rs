@@ -0,0 +1,181 @@
+//! Saving and loading game progress, stored as TOML.
+
+use std::fmt;
+
+use macroquad::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{assets::*, player::Player, simulation::Simulation, storage};
+
+/// Version written to new saves. Saves from newer versions of the game are rejected.
+pub const SAVE_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize)]
+pub struct SaveData {
+    pub version: u32,
+    pub level: String,
+    /// Whether the pumpkin has been given to the pumpkin guy.
+    pub delivered: bool,
+    /// Where the player came into the level.
+    pub entrance: [f32; 2],
+    pub player: PlayerState,
+    pub carrying: Option<PumpkinState>,
+    pub pumpkins: Vec<PumpkinState>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PlayerState {
+    pub pos: [f32; 2],
+    pub velocity: [f32; 2],
+    pub camera_pos: [f32; 2],
+    pub on_ground: bool,
+    pub facing_right: bool,
+    /// Seconds since the player jumped, while the jump can still be cut short.
+    pub jump_frames: f32,
+    pub coyote_time: f32,
+    pub jump_buffer: f32,
+    pub climbing: bool,
+    pub ground_friction: f32,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct PumpkinState {
+    pub pos: [f32; 2],
+    pub velocity: [f32; 2],
+    pub on_ground: bool,
+    pub friction: f32,
+    pub through_player: bool,
+    /// Id of the map object the pumpkin came from, for the editor.
+    pub id: Option<u32>,
+}
+impl PumpkinState {
+    fn capture(pumpkin: &Pumpkin) -> Self {
+        Self {
+            pos: pumpkin.pos.into(),
+            velocity: pumpkin.velocity.into(),
+            on_ground: pumpkin.on_ground,
+            friction: pumpkin.friction,
+            through_player: pumpkin.through_player,
+            id: pumpkin.id,
+        }
+    }
+    fn restore(&self) -> Pumpkin {
+        Pumpkin {
+            pos: self.pos.into(),
+            last_pos: self.pos.into(),
+            velocity: self.velocity.into(),
+            on_ground: self.on_ground,
+            friction: self.friction,
+            through_player: self.through_player,
+            id: self.id,
+        }
+    }
+}
+
+impl SaveData {
+    pub fn capture(simulation: &Simulation) -> Self {
+        let player = &simulation.player;
+        Self {
+            version: SAVE_VERSION,
+            level: simulation.level.clone(),
+            delivered: simulation.delivered,
+            entrance: simulation.entrance.into(),
+            player: PlayerState {
+                pos: player.pos.into(),
+                velocity: player.velocity.into(),
+                camera_pos: player.camera_pos.into(),
+                on_ground: player.on_ground,
+                facing_right: player.facing_right,
+                jump_frames: player.jump_frames,
+                coyote_time: player.coyote_time,
+                jump_buffer: player.jump_buffer,
+                climbing: player.climbing,
+                ground_friction: player.ground_friction,
+            },
+            carrying: player.carrying.as_ref().map(PumpkinState::capture),
+            pumpkins: simulation
+                .world
+                .pumpkins
+                .iter()
+                .map(PumpkinState::capture)
+                .collect(),
+        }
+    }
+    /// Continues the saved game in `world`, which should be a freshly loaded world of the saved level.
+    pub fn restore(&self, world: World) -> Simulation {
+        let mut simulation = Simulation::new(&self.level, world, None);
+        simulation.delivered = self.delivered;
+        simulation.entrance = self.entrance.into();
+        let player = &mut simulation.player;
+        player.pos = self.player.pos.into();
+        player.velocity = self.player.velocity.into();
+        player.camera_pos = self.player.camera_pos.into();
+        player.on_ground = self.player.on_ground;
+        player.facing_right = self.player.facing_right;
+        player.jump_frames = self.player.jump_frames;
+        player.coyote_time = self.player.coyote_time;
+        player.jump_buffer = self.player.jump_buffer;
+        player.climbing = self.player.climbing;
+        player.ground_friction = self.player.ground_friction;
+        player.carrying = self.carrying.as_ref().map(PumpkinState::restore);
+        player.snap();
+        // the player can't have gone through an exit they're standing in, or the level would
+        // have changed
+        let hitbox = Player::HITBOX.at(simulation.player.pos);
+        simulation.in_exit = simulation.world.exit_at(hitbox).is_some();
+        simulation.world.pumpkins = self.pumpkins.iter().map(PumpkinState::restore).collect();
+        simulation
+    }
+    pub fn to_toml(&self) -> Result<String, SaveError> {
+        toml::to_string(self).map_err(|err| SaveError::Invalid(err.to_string()))
+    }
+    pub fn from_toml(text: &str) -> Result<Self, SaveError> {
+        // check the version on its own first, since newer saves might not parse at all
+        #[derive(Deserialize)]
+        struct Header {
+            version: u32,
+        }
+        let header: Header =
+            toml::from_str(text).map_err(|err| SaveError::Invalid(err.to_string()))?;
+        if header.version > SAVE_VERSION {
+            return Err(SaveError::UnsupportedVersion(header.version));
+        }
+        toml::from_str(text).map_err(|err| SaveError::Invalid(err.to_string()))
+    }
+}
+
+#[derive(Debug)]
+pub enum SaveError {
+    NoSave,
+    Storage(String),
+    Invalid(String),
+    UnsupportedVersion(u32),
+    Level(String),
+}
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::NoSave => write!(f, "no save found"),
+            SaveError::Storage(err) => write!(f, "couldn't access save: {err}"),
+            SaveError::Invalid(err) => write!(f, "save is corrupted: {err}"),
+            SaveError::UnsupportedVersion(version) => write!(
+                f,
+                "save is from a newer version of the game (version {version}, expected {SAVE_VERSION} or older)"
+            ),
+            SaveError::Level(err) => write!(f, "couldn't load saved level: {err}"),
+        }
+    }
+}
+impl std::error::Error for SaveError {}
+
+const SAVE_FILE: &str = "save.toml";
+
+pub fn write(save: &SaveData) -> Result<(), SaveError> {
+    storage::write(SAVE_FILE, &save.to_toml()?).map_err(SaveError::Storage)
+}
+pub fn read() -> Result<SaveData, SaveError> {
+    let text = storage::read(SAVE_FILE)
+        .map_err(SaveError::Storage)?
+        .ok_or(SaveError::NoSave)?;
+    SaveData::from_toml(&text)
+}
@@ -0,0 +1,804 @@
+//! The game itself, advanced one tick at a time.
+//!
+//! Nothing here opens a window, draws or reads input, so the game can run headlessly, like in tests.
+
+use macroquad::prelude::*;
+
+use crate::{
+    assets::World,
+    input::{Action, TickInput},
+    physics::PhysicsConfig,
+    player::Player,
+    utils::TICK,
+};
+
+/// Something that happened during a tick, which the game has to respond to.
+#[derive(Debug, PartialEq)]
+pub enum Event {
+    /// The pumpkin was given to the pumpkin guy.
+    Delivered,
+    /// The player went through an exit, to the spawn point `spawn` of `level`.
+    Exit {
+        level: String,
+        spawn: Option<String>,
+    },
+}
+
+pub struct Simulation {
+    /// Name of the level currently loaded.
+    pub level: String,
+    pub world: World,
+    pub player: Player,
+    /// Whether the pumpkin has been given to the pumpkin guy.
+    pub delivered: bool,
+    /// Where the player entered the level, and goes back to after touching a hazard.
+    pub entrance: Vec2,
+    /// Whether the player was in an exit last tick. Exits that aren't doors only fire once the
+    /// player has left them, so arriving on top of one doesn't send the player straight back.
+    pub in_exit: bool,
+    pub physics: PhysicsConfig,
+}
+impl Simulation {
+    /// Starts a new game in `world`, at the spawn point named `spawn`.
+    pub fn new(level: &str, world: World, spawn: Option<&str>) -> Self {
+        let mut simulation = Self {
+            level: level.to_string(),
+            world,
+            player: Player::default(),
+            delivered: false,
+            entrance: Vec2::ZERO,
+            in_exit: false,
+            physics: PhysicsConfig::default(),
+        };
+        simulation.spawn_player(spawn);
+        simulation
+    }
+    /// Swaps the current world for another level, keeping the player and whatever they carry.
+    pub fn enter_world(&mut self, level: &str, world: World, spawn: Option<&str>) {
+        self.world = world;
+        self.level = level.to_string();
+        self.spawn_player(spawn);
+    }
+    /// Replaces the map of the current level, keeping the pumpkins where they are.
+    pub fn reload_world(&mut self, world: World) {
+        self.world.reload(world);
+        self.player.poi_location = self.poi_location();
+    }
+    /// Where the pumpkin guy stands, if he's in this level.
+    pub fn poi_location(&self) -> Option<Vec2> {
+        self.world.npc("pumpkin_guy").map(|f| f.pos)
+    }
+    fn spawn_player(&mut self, spawn: Option<&str>) {
+        self.entrance = self.world.spawn_point(spawn);
+        self.respawn_player();
+    }
+    /// Puts the player back at the entrance of the level, like after touching a hazard.
+    fn respawn_player(&mut self) {
+        self.teleport(self.entrance);
+    }
+    /// Moves the player to `pos`, stopping them.
+    pub fn teleport(&mut self, pos: Vec2) {
+        self.player.pos = pos;
+        self.player.velocity = Vec2::ZERO;
+        self.player.climbing = false;
+        self.player.camera_pos = self.player.pos - vec2(0.0, 3.0 * 8.0);
+        self.player.poi_location = self.poi_location();
+        self.player.snap();
+        self.in_exit = self.world.exit_at(Player::HITBOX.at(pos)).is_some();
+    }
+    /// Whether the player is close enough to the pumpkin guy to talk to him.
+    pub fn near_poi(&self) -> bool {
+        self.player
+            .poi_location
+            .is_some_and(|f| self.player.pos.distance(f) <= 16.0)
+    }
+    /// Advances the game by one tick.
+    pub fn tick(&mut self, input: &TickInput) -> Option<Event> {
+        self.player
+            .update(&mut self.world, input, &self.physics, TICK);
+        if !input.noclip
+            && self
+                .world
+                .tiles()
+                .touching(Player::HITBOX.at(self.player.pos))
+                .any(|f| f.hazard)
+        {
+            self.respawn_player();
+        }
+        // falling pumpkins land on the player's head, rather than falling through them
+        let player = Player::HITBOX.at(self.player.pos);
+        self.world.update_pumpkins(&self.physics, TICK, player);
+
+        if self.near_poi() && self.player.carrying.is_some() && input.pressed(Action::Interact) {
+            self.player.carrying = None;
+            self.delivered = true;
+            return Some(Event::Delivered);
+        }
+
+        let exit = self.world.exit_at(Player::HITBOX.at(self.player.pos));
+        let entered = !self.in_exit;
+        self.in_exit = exit.is_some();
+        exit.filter(|exit| {
+            if exit.door {
+                input.pressed(Action::Up)
+            } else {
+                entered
+            }
+        })
+        .map(|exit| Event::Exit {
+            level: exit.level.clone(),
+            spawn: exit.spawn.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        asset_source::AssetSource,
+        assets::{Pumpkin, TileProperties, WorldLayer},
+        input::Action::*,
+        levels::LevelRegistry,
+        replay::Replay,
+        save::SaveData,
+        tiled::parse_map,
+    };
+    use std::collections::HashMap;
+
+    fn load_world() -> World {
+        LevelRegistry::default()
+            .load(&AssetSource::default(), "world")
+            .unwrap()
+    }
+
+    /// A gid past the end of the tileset, for tiles that tests give whatever properties they
+    /// need. Solid without any. The gid after it is free for a second kind.
+    const TEST_TILE: i16 = 1000;
+
+    /// Where each named object in the world's map is, like pumpkins where they start out and the
+    /// waypoints on the "Route" layer, which the level is played through by.
+    fn named_objects() -> HashMap<String, Vec2> {
+        let source = AssetSource::default();
+        let path = LevelRegistry::default().path("world").unwrap();
+        let map = parse_map(&source.read_string(path).unwrap()).unwrap();
+        map.objects()
+            .filter(|f| !f.name.is_empty())
+            .map(|f| (f.name.clone(), f.pos))
+            .collect()
+    }
+
+    /// Where the player stands on top of the tile at `x`, `y`.
+    fn on_tile(x: i16, y: i16) -> Vec2 {
+        vec2(x as f32, y as f32 - 1.0) * 8.0
+    }
+
+    /// Plays the game by reacting to where the player ends up, rather than by exact timings,
+    /// so small changes to movement don't break the scripts.
+    struct Driver {
+        simulation: Simulation,
+        replay: Replay,
+        events: Vec<Event>,
+        /// Where each named object in the map is.
+        objects: HashMap<String, Vec2>,
+    }
+    impl Driver {
+        fn new() -> Self {
+            let simulation = Simulation::new("world", load_world(), None);
+            let replay = Replay::new(SaveData::capture(&simulation), simulation.physics);
+            let mut driver = Self {
+                objects: named_objects(),
+                simulation,
+                replay,
+                events: Vec::new(),
+            };
+            driver.wait(30);
+            driver
+        }
+        fn tick(&mut self, down: &[Action], pressed: &[Action]) {
+            let input = TickInput::new(down, pressed);
+            self.replay.record(input);
+            self.events.extend(self.simulation.tick(&input));
+        }
+        fn wait(&mut self, ticks: u32) {
+            for _ in 0..ticks {
+                self.tick(&[], &[]);
+            }
+        }
+        fn hold(&mut self, action: Action, ticks: u32) {
+            for _ in 0..ticks {
+                self.tick(&[action], &[]);
+            }
+        }
+        /// Walks until within a pixel of `x`, or stuck, then waits to stand still.
+        fn walk_to(&mut self, x: f32) {
+            for _ in 0..2000 {
+                let dx = x - self.simulation.player.pos.x;
+                if dx.abs() < 1.0 {
+                    break;
+                }
+                self.hold(if dx > 0.0 { MoveRight } else { MoveLeft }, 1);
+            }
+            self.settle();
+        }
+        fn settle(&mut self) {
+            for _ in 0..120 {
+                let player = &self.simulation.player;
+                if player.velocity.x == 0.0 && player.on_ground {
+                    break;
+                }
+                self.wait(1);
+            }
+        }
+        /// Jumps while holding `held`, until landing.
+        fn jump(&mut self, held: &[Action]) {
+            let held = [held, &[Jump]].concat();
+            self.tick(&held, &[Jump]);
+            for _ in 0..300 {
+                if self.simulation.player.on_ground {
+                    break;
+                }
+                self.tick(&held, &[]);
+            }
+        }
+        fn pick_up(&mut self) {
+            self.tick(&[], &[Interact]);
+            let player = &self.simulation.player;
+            assert!(
+                player.carrying.is_some(),
+                "no pumpkin to pick up at {}",
+                player.pos
+            );
+        }
+        /// Puts the carried pumpkin down at the player's feet, and waits for it to land.
+        fn drop(&mut self) {
+            self.tick(&[Down], &[Interact]);
+            assert!(self.simulation.player.carrying.is_none());
+            self.wait(20);
+        }
+        fn deliver(&mut self) {
+            self.tick(&[], &[Interact]);
+        }
+
+        /// Whether bodies can't pass through `point`.
+        fn solid_at(&self, point: Vec2) -> bool {
+            let tile = (point / 8.0).floor();
+            let world = &self.simulation.world;
+            world.tiles().solid(tile.x as i16, tile.y as i16).is_some()
+                || world
+                    .pumpkins
+                    .iter()
+                    .any(|f| !f.through_player && Pumpkin::HITBOX.at(f.pos).contains(point))
+        }
+        /// Whether there's something to stand on at `point`.
+        fn ground_at(&self, point: Vec2) -> bool {
+            let tile = (point / 8.0).floor();
+            let tiles = self.simulation.world.tiles();
+            self.solid_at(point) || tiles.one_way(tile.x as i16, tile.y as i16).is_some()
+        }
+        /// Gets the player to `target`, walking there and jumping over whatever's in the way.
+        /// Jumps off edges towards a higher target, and walks off them towards a lower one.
+        fn go_to(&mut self, target: Vec2) {
+            for _ in 0..3000 {
+                let player = &self.simulation.player;
+                let pos = player.pos;
+                let dx = target.x - pos.x;
+                let higher = target.y < pos.y - 1.0;
+                if dx.abs() < 1.0 {
+                    self.settle();
+                    let pos = self.simulation.player.pos;
+                    if pos.distance(target) < 1.0 {
+                        return;
+                    }
+                    // straight up, through one way platforms
+                    if target.y < pos.y - 1.0 && (target.x - pos.x).abs() < 1.0 {
+                        self.jump(&[]);
+                    }
+                    continue;
+                }
+                let direction = if dx > 0.0 { MoveRight } else { MoveLeft };
+                if !player.on_ground {
+                    self.hold(direction, 1);
+                    continue;
+                }
+                let front = if dx > 0.0 { pos.x + 8.5 } else { pos.x - 0.5 };
+                let blocked = self.solid_at(vec2(front, pos.y + 4.0))
+                    || self.solid_at(vec2(front, pos.y + 7.5));
+                if blocked || (higher && !self.ground_at(vec2(front, pos.y + 8.5))) {
+                    self.jump(&[direction]);
+                } else {
+                    self.hold(direction, 1);
+                }
+            }
+            panic!(
+                "couldn't get from {} to {target}",
+                self.simulation.player.pos
+            );
+        }
+        /// Where the object called `name` is in the map.
+        fn object(&self, name: &str) -> Vec2 {
+            *self
+                .objects
+                .get(name)
+                .unwrap_or_else(|| panic!("no object named \"{name}\" in the map"))
+        }
+        /// Goes to the waypoint called `name`.
+        fn go_to_waypoint(&mut self, name: &str) {
+            self.go_to(self.object(name));
+        }
+        /// Picks up the pumpkin called `name` in the map, walking to it in `direction`.
+        fn fetch(&mut self, name: &str, direction: Action) {
+            let start = self.object(name);
+            let pumpkin = self
+                .simulation
+                .world
+                .pumpkins
+                .iter()
+                .map(|f| f.pos)
+                .min_by(|a, b| a.distance(start).total_cmp(&b.distance(start)))
+                .unwrap();
+            let side = if direction == MoveRight { -8.0 } else { 8.0 };
+            self.go_to(pumpkin + vec2(side, 0.0));
+            self.pick_up();
+        }
+        /// Puts the carried pumpkin down against the wall in `direction` and climbs onto the
+        /// wall with it, for walls too high to jump.
+        fn step_up(&mut self, direction: Action) {
+            let step = if direction == MoveRight { 1 } else { -1 };
+            let pos = self.simulation.player.pos;
+            let tiles = self.simulation.world.tiles();
+            let row = ((pos.y + 4.0) / 8.0).floor() as i16;
+            let mut column = (pos.x / 8.0).floor() as i16;
+            while tiles.solid(column, row).is_none() {
+                column += step;
+            }
+            let mut top = row;
+            while tiles.solid(column, top - 1).is_some() {
+                top -= 1;
+            }
+            self.go_to(vec2((column - step) as f32 * 8.0, pos.y));
+            self.drop();
+            self.go_to(on_tile(column, top));
+        }
+        /// Puts the carried pumpkin down at the waypoint called `name`.
+        fn drop_at(&mut self, name: &str) {
+            self.go_to_waypoint(name);
+            self.drop();
+        }
+        /// Climbs onto the pumpkin that was just put down.
+        fn climb_onto_dropped(&mut self) {
+            let pumpkin = self.simulation.world.pumpkins.last().unwrap().pos;
+            self.go_to(pumpkin - vec2(0.0, 8.0));
+        }
+        /// Brings the carried pumpkin to the pumpkin guy.
+        fn deliver_to_pumpkin_guy(&mut self) {
+            let guy = self.simulation.poi_location().unwrap();
+            self.go_to(guy - vec2(8.0, 0.0));
+            assert!(self.simulation.near_poi());
+            self.deliver();
+        }
+    }
+
+    /// Brings the pumpkin up on the highest ledge back to the pumpkin guy, using the other
+    /// pumpkins as steps along the way.
+    fn complete_world(driver: &mut Driver) {
+        // the first wall is too high to jump, so the pumpkin by the spawn point becomes a step
+        driver.fetch("by_spawn", MoveRight);
+        driver.step_up(MoveRight);
+
+        // the pumpkin in the dead end past the floating block is brought out, to climb the block
+        driver.fetch("dead_end", MoveRight);
+        driver.drop_at("dead_end_step");
+        driver.climb_onto_dropped();
+        driver.go_to_waypoint("floating_block");
+        driver.go_to_waypoint("past_floating_block");
+
+        // the next one is carried up the one way platforms, to climb the wall after them
+        driver.fetch("platforms", MoveRight);
+        for waypoint in ["platform_1", "platform_2", "platform_3"] {
+            driver.go_to_waypoint(waypoint);
+        }
+        driver.step_up(MoveRight);
+
+        // over the pit to the highest ledge, and back over the one way platforms above the pit
+        driver.fetch("ledge", MoveRight);
+        for waypoint in ["above_pit_1", "above_pit_2", "above_pit_3", "above_pit_4"] {
+            driver.go_to_waypoint(waypoint);
+        }
+        driver.deliver_to_pumpkin_guy();
+    }
+
+    #[test]
+    fn completes_world() {
+        let mut driver = Driver::new();
+        complete_world(&mut driver);
+        assert_eq!(driver.events, [Event::Delivered]);
+        assert!(driver.simulation.delivered);
+        assert!(driver.simulation.player.carrying.is_none());
+    }
+
+    #[test]
+    fn replays_reproduce_runs() {
+        let mut driver = Driver::new();
+        complete_world(&mut driver);
+
+        let replay = Replay::from_toml(&driver.replay.to_toml().unwrap()).unwrap();
+        assert_eq!(replay.ticks(), driver.replay.ticks());
+        let mut simulation = replay.start.restore(load_world());
+        simulation.physics = replay.physics;
+        let events: Vec<Event> = replay
+            .inputs()
+            .filter_map(|input| simulation.tick(&input))
+            .collect();
+        assert_eq!(events, driver.events);
+        assert!(simulation.delivered);
+        assert_eq!(simulation.player.pos, driver.simulation.player.pos);
+
+        let physics = PhysicsConfig {
+            gravity: 500.0,
+            ..Default::default()
+        };
+        let replay = Replay::new(SaveData::capture(&simulation), physics);
+        let replay = Replay::from_toml(&replay.to_toml().unwrap()).unwrap();
+        assert_eq!(replay.physics, physics);
+    }
+
+    #[test]
+    fn saves_continue_exactly() {
+        let mut driver = Driver::new();
+        driver.fetch("by_spawn", MoveRight);
+        driver.simulation.entrance = driver.simulation.player.pos;
+        // mid jump, so the state between ticks matters
+        driver.tick(&[MoveRight, Jump], &[Jump]);
+        driver.tick(&[MoveRight, Jump], &[]);
+        // buffered for when the player lands
+        driver.tick(&[MoveRight, Jump], &[Jump]);
+        assert!(driver.simulation.player.jump_buffer > 0.0);
+
+        let save = SaveData::from_toml(&SaveData::capture(&driver.simulation).to_toml().unwrap());
+        let mut simulation = save.unwrap().restore(load_world());
+        assert_eq!(simulation.entrance, driver.simulation.entrance);
+        assert_eq!(
+            simulation.player.jump_buffer,
+            driver.simulation.player.jump_buffer
+        );
+        for _ in 0..60 {
+            let input = TickInput::new(&[MoveRight, Jump], &[]);
+            driver.simulation.tick(&input);
+            simulation.tick(&input);
+        }
+        assert_eq!(
+            SaveData::capture(&simulation).to_toml().unwrap(),
+            SaveData::capture(&driver.simulation).to_toml().unwrap()
+        );
+    }
+
+    #[test]
+    fn stacks_pumpkins() {
+        let mut driver = Driver::new();
+        // on the ground by the spawn point, and on the first one way platform left of the pumpkin guy
+        driver.simulation.world.pumpkins = [
+            vec2(40.0, -60.0),
+            vec2(43.0, -30.0),
+            vec2(38.0, -90.0),
+            vec2(1512.0, -230.0),
+            vec2(1512.0, -200.0),
+        ]
+        .map(Pumpkin::new)
+        .into();
+        driver.wait(120);
+        let positions: Vec<Vec2> = driver
+            .simulation
+            .world
+            .pumpkins
+            .iter()
+            .map(|f| f.pos)
+            .collect();
+        assert_eq!(
+            positions,
+            [
+                vec2(40.0, -16.0),
+                vec2(43.0, -8.0),
+                vec2(38.0, -24.0),
+                vec2(1512.0, -168.0),
+                vec2(1512.0, -160.0),
+            ]
+        );
+
+        // and the player can stand on top
+        driver.simulation.player.pos = vec2(39.0, -60.0);
+        driver.wait(60);
+        assert!(driver.simulation.player.on_ground);
+        assert_eq!(driver.simulation.player.pos.y, -32.0);
+        assert_eq!(driver.simulation.world.pumpkins[2].pos, vec2(38.0, -24.0));
+    }
+
+    #[test]
+    fn dropped_pumpkins_land_at_the_players_feet() {
+        let mut driver = Driver::new();
+        driver.walk_to(240.0);
+        driver.pick_up();
+        let player = driver.simulation.player.pos;
+        driver.drop();
+        let dropped = driver.simulation.world.pumpkins.last().unwrap();
+        assert_eq!(dropped.pos, player);
+        assert!(dropped.on_ground);
+        assert_eq!(driver.simulation.player.pos, player);
+
+        // and is solid again once they walk away
+        driver.walk_to(player.x - 16.0);
+        assert!(
+            !driver
+                .simulation
+                .world
+                .pumpkins
+                .last()
+                .unwrap()
+                .through_player
+        );
+        driver.walk_to(player.x);
+        assert_eq!(driver.simulation.player.pos.x, player.x - 8.0);
+    }
+
+    #[test]
+    fn throws_in_the_direction_the_stick_is_closest_to() {
+        let throw = |x, y| {
+            let mut driver = Driver::new();
+            driver.walk_to(240.0);
+            driver.pick_up();
+            let input = TickInput::new(&[], &[Interact]).with_axis(vec2(x, y));
+            driver.simulation.tick(&input);
+            driver.simulation.world.pumpkins.last().unwrap().velocity
+        };
+        // nearly straight to the side, rather than up a little
+        let side = throw(0.95, -0.2);
+        assert!(side.x > 0.0 && side.y >= 0.0);
+        // nearly straight down is still a drop
+        assert_eq!(throw(0.1, 0.99).x, 0.0);
+        let diagonal = throw(0.6, -0.8);
+        assert!(diagonal.x > 0.0 && diagonal.y < 0.0);
+    }
+
+    #[test]
+    fn pumpkins_land_on_the_player() {
+        let mut driver = Driver::new();
+        driver.walk_to(240.0);
+        let player = driver.simulation.player.pos;
+        let mut pumpkin = Pumpkin::new(player - vec2(0.0, 40.0));
+        pumpkin.on_ground = false;
+        driver.simulation.world.pumpkins.push(pumpkin);
+        driver.wait(60);
+
+        let landed = driver.simulation.world.pumpkins.last().unwrap();
+        assert_eq!(landed.pos, player - vec2(0.0, 7.0));
+        assert!(landed.on_ground);
+        assert_eq!(driver.simulation.player.pos, player);
+        assert!(driver.simulation.player.on_ground);
+    }
+
+    #[test]
+    fn climbs_and_respawns_at_hazards() {
+        let mut driver = Driver::new();
+        let world = &mut driver.simulation.world;
+        let vine = TileProperties {
+            solid: Some(false),
+            climbable: true,
+            ..Default::default()
+        };
+        let spikes = TileProperties {
+            solid: Some(false),
+            hazard: true,
+            ..Default::default()
+        };
+        world
+            .properties
+            .extend([(TEST_TILE, vine), (TEST_TILE + 1, spikes)]);
+        let start = driver.simulation.player.pos;
+        let tile = (start / 8.0).floor();
+        let (x, y) = (tile.x as i16, tile.y as i16);
+        for y in y - 4..=y {
+            world.set_tile(WorldLayer::Collision, x, y, TEST_TILE);
+        }
+
+        driver.hold(Up, 20);
+        let player = &driver.simulation.player;
+        assert!(player.climbing);
+        assert!(player.pos.y < start.y - 8.0);
+        // stays put when letting go
+        let pos = player.pos;
+        driver.wait(10);
+        assert_eq!(driver.simulation.player.pos, pos);
+        // jumps off
+        driver.jump(&[MoveRight]);
+        assert!(!driver.simulation.player.climbing);
+
+        driver.walk_to(start.x + 24.0);
+        let tile = (driver.simulation.player.pos / 8.0).floor();
+        let world = &mut driver.simulation.world;
+        world.set_tile(
+            WorldLayer::Collision,
+            tile.x as i16,
+            tile.y as i16,
+            TEST_TILE + 1,
+        );
+        driver.wait(1);
+        let entrance = driver.simulation.entrance;
+        assert_eq!(driver.simulation.player.pos, entrance);
+    }
+
+    /// Height the player stands at on `ledge`.
+    const LEDGE_Y: f32 = -808.0;
+
+    /// Puts the player on a ledge of their own, high above the level, which ends at `x = 64`.
+    fn ledge() -> Driver {
+        let mut driver = Driver::new();
+        for x in 0..8 {
+            let world = &mut driver.simulation.world;
+            world.set_tile(WorldLayer::Collision, x, -100, TEST_TILE);
+        }
+        driver.simulation.teleport(vec2(32.0, LEDGE_Y));
+        driver.wait(5);
+        driver
+    }
+
+    #[test]
+    fn jumps_higher_when_holding_jump() {
+        let height = |held| {
+            let mut driver = ledge();
+            driver.tick(&[Jump], &[Jump]);
+            let mut top = LEDGE_Y;
+            for tick in 0..60 {
+                let down: &[Action] = if tick < held { &[Jump] } else { &[] };
+                driver.tick(down, &[]);
+                top = top.min(driver.simulation.player.pos.y);
+            }
+            LEDGE_Y - top
+        };
+        let full = height(60);
+        assert!(full > 16.0);
+        assert!(height(1) < full * 0.7);
+        assert!(height(1) < height(6));
+    }
+
+    #[test]
+    fn jumps_just_after_walking_off_ledges() {
+        let jumps = |late| {
+            let mut driver = ledge();
+            for _ in 0..600 {
+                if !driver.simulation.player.on_ground {
+                    break;
+                }
+                driver.hold(MoveRight, 1);
+            }
+            driver.wait(late);
+            driver.tick(&[Jump], &[Jump]);
+            driver.simulation.player.velocity.y < 0.0
+        };
+        assert!(jumps(3));
+        assert!(!jumps(10));
+    }
+
+    #[test]
+    fn buffers_jumps_pressed_before_landing() {
+        let jumps = |early| {
+            let mut driver = ledge();
+            driver.simulation.teleport(vec2(32.0, LEDGE_Y - 40.0));
+            for _ in 0..600 {
+                if driver.simulation.player.pos.y > LEDGE_Y - early {
+                    break;
+                }
+                driver.wait(1);
+            }
+            driver.tick(&[Jump], &[Jump]);
+            for _ in 0..60 {
+                if driver.simulation.player.on_ground {
+                    break;
+                }
+                driver.hold(Jump, 1);
+            }
+            driver.hold(Jump, 10);
+            driver.simulation.player.pos.y < LEDGE_Y - 8.0
+        };
+        assert!(jumps(6.0));
+        assert!(!jumps(40.0));
+    }
+
+    /// A small level with a floor, a spawn point `spawn` at `spawn_x` and an exit at `exit_x`
+    /// leading to the spawn point `to` of the level `level`.
+    fn exit_level(spawn: &str, spawn_x: f32, exit_x: f32, level: &str, to: &str) -> World {
+        let tmx = format!(
+            r#"<map width="16" height="4" tilewidth="8" tileheight="8">
+                <tileset firstgid="1" name="test" tilewidth="8" tileheight="8" tilecount="4" columns="2"/>
+                <layer id="1" name="Collision">
+                    <data encoding="csv">{floor}</data>
+                </layer>
+                <objectgroup id="2" name="Objects">
+                    <object id="3" name="{spawn}" type="player_spawn" x="{spawn_x}" y="16"/>
+                    <object id="4" type="exit" x="{exit_x}" y="0" width="16" height="24">
+                        <properties>
+                            <property name="level" value="{level}"/>
+                            <property name="spawn" value="{to}"/>
+                        </properties>
+                    </object>
+                </objectgroup>
+            </map>"#,
+            floor = ["0"; 48]
+                .iter()
+                .chain(&["1"; 16])
+                .copied()
+                .collect::<Vec<_>>()
+                .join(","),
+        );
+        World::from_tmx(&tmx, |_| None).unwrap()
+    }
+
+    #[test]
+    fn goes_through_doors_to_other_levels() {
+        let levels = LevelRegistry::default();
+        let mut driver = Driver::new();
+        let door = driver.simulation.world.spawn_point(Some("cellar"));
+        driver.walk_to(door.x);
+        assert!(driver.events.is_empty());
+        driver.tick(&[], &[Up]);
+        let Some(Event::Exit { level, spawn }) = driver.events.pop() else {
+            panic!("didn't go through the door");
+        };
+        assert_eq!(level, "cellar");
+        let cellar = levels.load(&AssetSource::default(), &level).unwrap();
+        driver
+            .simulation
+            .enter_world(&level, cellar, spawn.as_deref());
+
+        // and back out, to where the door is
+        driver.wait(30);
+        driver.tick(&[], &[Up]);
+        let Some(Event::Exit { level, spawn }) = driver.events.pop() else {
+            panic!("didn't go back through the door");
+        };
+        driver
+            .simulation
+            .enter_world(&level, load_world(), spawn.as_deref());
+        driver.settle();
+        assert_eq!(driver.simulation.level, "world");
+        assert_eq!(driver.simulation.player.pos, door);
+    }
+
+    #[test]
+    fn exits_only_fire_once_left() {
+        let mut simulation =
+            Simulation::new("a", exit_level("start", 8.0, 96.0, "b", "arrive"), None);
+        let walk = |simulation: &mut Simulation, held: &[Action], ticks: u32| {
+            let input = TickInput::new(held, &[]);
+            (0..ticks).find_map(|_| simulation.tick(&input))
+        };
+        let event = walk(&mut simulation, &[MoveRight], 120);
+        assert_eq!(
+            event,
+            Some(Event::Exit {
+                level: "b".to_string(),
+                spawn: Some("arrive".to_string())
+            })
+        );
+
+        // arriving on top of the exit back doesn't use it
+        simulation.enter_world(
+            "b",
+            exit_level("arrive", 8.0, 0.0, "a", "start"),
+            Some("arrive"),
+        );
+        assert!(simulation.in_exit);
+        assert_eq!(walk(&mut simulation, &[], 30), None);
+
+        // but it does after stepping out of it
+        walk(&mut simulation, &[MoveRight], 30);
+        assert!(!simulation.in_exit);
+        let event = walk(&mut simulation, &[MoveLeft], 120);
+        assert_eq!(
+            event,
+            Some(Event::Exit {
+                level: "a".to_string(),
+                spawn: Some("start".to_string())
+            })
+        );
+    }
+}
@@ -0,0 +1,67 @@
+//! Small files the game keeps between runs, like saves and settings.
+//!
+//! Stored in the user's data directory on native builds and in the browser's localStorage on the web.
+
+#[cfg(not(target_arch = "wasm32"))]
+mod platform {
+    use std::{io::ErrorKind, path::PathBuf};
+
+    fn path(name: &str) -> Result<PathBuf, String> {
+        let dir = dirs::data_dir().ok_or_else(|| "no data directory".to_string())?;
+        Ok(dir.join("pumpkin").join(name))
+    }
+    pub fn write(name: &str, text: &str) -> Result<(), String> {
+        let path = path(name)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|err| err.to_string())?;
+        }
+        std::fs::write(path, text).map_err(|err| err.to_string())
+    }
+    pub fn read(name: &str) -> Result<Option<String>, String> {
+        match std::fs::read_to_string(path(name)?) {
+            Ok(text) => Ok(Some(text)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.to_string()),
+        }
+    }
+}
+
+/// Uses the `pumpkin_storage` plugin in `web/storage.js`.
+#[cfg(target_arch = "wasm32")]
+mod platform {
+    unsafe extern "C" {
+        fn pumpkin_storage_set(key: *const u8, key_len: usize, value: *const u8, value_len: usize);
+        /// Length in bytes of the stored value, or -1 if there is none.
+        fn pumpkin_storage_len(key: *const u8, key_len: usize) -> i32;
+        fn pumpkin_storage_get(key: *const u8, key_len: usize, out: *mut u8, out_len: usize);
+    }
+
+    /// Lets the plugin loader in `gl.js` know the plugin is in use.
+    #[unsafe(no_mangle)]
+    pub extern "C" fn pumpkin_storage_crate_version() -> u32 {
+        1
+    }
+
+    fn key(name: &str) -> String {
+        format!("pumpkin/{name}")
+    }
+    pub fn write(name: &str, text: &str) -> Result<(), String> {
+        let key = key(name);
+        unsafe { pumpkin_storage_set(key.as_ptr(), key.len(), text.as_ptr(), text.len()) };
+        Ok(())
+    }
+    pub fn read(name: &str) -> Result<Option<String>, String> {
+        let key = key(name);
+        let len = unsafe { pumpkin_storage_len(key.as_ptr(), key.len()) };
+        if len < 0 {
+            return Ok(None);
+        }
+        let mut bytes = vec![0; len as usize];
+        unsafe { pumpkin_storage_get(key.as_ptr(), key.len(), bytes.as_mut_ptr(), bytes.len()) };
+        String::from_utf8(bytes)
+            .map(Some)
+            .map_err(|err| err.to_string())
+    }
+}
+
+pub use platform::{read, write};
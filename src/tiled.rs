@@ -0,0 +1,985 @@
+//! Loader for maps (`.tmx`) and tilesets (`.tsx`) made in Tiled.
+
+use std::{collections::HashMap, fmt, io::Read};
+
+use base64::Engine;
+use flate2::read::{GzDecoder, ZlibDecoder};
+use macroquad::prelude::*;
+use roxmltree::{Document, Node};
+
+/// Top bits of a gid are used by Tiled for flip/rotation flags.
+pub const GID_MASK: u32 = 0x0fff_ffff;
+
+#[derive(Debug)]
+pub enum TiledError {
+    Xml(roxmltree::Error),
+    Map(String),
+    Tileset(String),
+    Layer {
+        layer: String,
+        chunk: Option<(i32, i32)>,
+        reason: String,
+    },
+}
+impl fmt::Display for TiledError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TiledError::Xml(err) => write!(f, "invalid xml: {err}"),
+            TiledError::Map(reason) => write!(f, "map: {reason}"),
+            TiledError::Tileset(reason) => write!(f, "tileset: {reason}"),
+            TiledError::Layer {
+                layer,
+                chunk: Some((x, y)),
+                reason,
+            } => write!(f, "layer \"{layer}\", chunk ({x}, {y}): {reason}"),
+            TiledError::Layer {
+                layer,
+                chunk: None,
+                reason,
+            } => write!(f, "layer \"{layer}\": {reason}"),
+        }
+    }
+}
+impl std::error::Error for TiledError {}
+impl From<roxmltree::Error> for TiledError {
+    fn from(value: roxmltree::Error) -> Self {
+        TiledError::Xml(value)
+    }
+}
+
+#[expect(dead_code)]
+pub struct Map {
+    pub width: u32,
+    pub height: u32,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub infinite: bool,
+    pub tilesets: Vec<MapTileset>,
+    /// All tile layers, with groups flattened.
+    pub layers: Vec<TileLayer>,
+    /// All object layers, with groups flattened.
+    pub object_groups: Vec<ObjectGroup>,
+    /// Id the next object added to the map gets.
+    pub next_object_id: u32,
+}
+impl Map {
+    pub fn layer(&self, name: &str) -> Option<&TileLayer> {
+        self.layers.iter().find(|f| f.name == name)
+    }
+    /// Iterates over the objects of every object layer.
+    pub fn objects(&self) -> impl Iterator<Item = &Object> {
+        self.object_groups.iter().flat_map(|f| f.objects.iter())
+    }
+    /// Parses the tileset referenced by the map, resolving external tilesets through `load`.
+    /// Maps can only use one tileset.
+    pub fn tileset(
+        &self,
+        load: impl FnOnce(&str) -> Option<String>,
+    ) -> Result<Option<Tileset>, TiledError> {
+        if self.tilesets.len() > 1 {
+            return Err(TiledError::Map(format!(
+                "only one tileset is supported, found {}",
+                self.tilesets.len()
+            )));
+        }
+        match self.tilesets.first() {
+            None => Ok(None),
+            Some(MapTileset::Embedded(tileset)) => Ok(Some(tileset.clone())),
+            Some(MapTileset::External { first_gid, source }) => {
+                let xml = load(source)
+                    .ok_or_else(|| TiledError::Tileset(format!("couldn't load \"{source}\"")))?;
+                parse_tileset(&xml, *first_gid).map(Some)
+            }
+        }
+    }
+}
+
+pub enum MapTileset {
+    External { first_gid: u32, source: String },
+    Embedded(Tileset),
+}
+
+#[derive(Clone)]
+#[expect(dead_code)]
+pub struct Tileset {
+    pub first_gid: u32,
+    pub name: String,
+    pub tile_width: u32,
+    pub tile_height: u32,
+    pub tile_count: u32,
+    pub columns: u32,
+    /// Custom properties of each tile that has any, by id within the tileset.
+    pub tiles: HashMap<u32, Properties>,
+    /// Frames of each animated tile, by id within the tileset.
+    pub animations: HashMap<u32, Vec<Frame>>,
+}
+
+/// One frame of a tile animation.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Frame {
+    /// Id of the tile shown, within the tileset.
+    pub tile: u32,
+    /// How long the frame is shown for, in milliseconds.
+    pub duration: u32,
+}
+
+#[expect(dead_code)]
+pub struct TileLayer {
+    pub id: u32,
+    pub name: String,
+    pub opacity: f32,
+    pub visible: bool,
+    pub offset: Vec2,
+    /// Finite maps are loaded as a single chunk covering the whole layer.
+    pub chunks: Vec<TileChunk>,
+}
+
+pub struct TileChunk {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    /// Gids, with any flip flags in the bits outside `GID_MASK`. 0 is an empty tile.
+    pub tiles: Vec<u32>,
+}
+
+#[expect(dead_code)]
+pub struct ObjectGroup {
+    pub id: u32,
+    pub name: String,
+    pub visible: bool,
+    pub objects: Vec<Object>,
+}
+
+pub struct Object {
+    pub id: u32,
+    pub name: String,
+    /// The object's type, called class in newer versions of Tiled.
+    pub kind: String,
+    /// Top left corner, in pixels. Includes the offset of the object layer.
+    pub pos: Vec2,
+    pub size: Vec2,
+    pub properties: Properties,
+}
+impl Object {
+    pub fn rect(&self) -> Rect {
+        Rect::new(self.pos.x, self.pos.y, self.size.x, self.size.y)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Property {
+    String(String),
+    Int(i64),
+    Float(f32),
+    Bool(bool),
+}
+
+/// Custom properties set on a Tiled object, tile or layer.
+#[derive(Clone, Debug, Default)]
+pub struct Properties(pub HashMap<String, Property>);
+impl Properties {
+    pub fn get(&self, name: &str) -> Option<&Property> {
+        self.0.get(name)
+    }
+    pub fn string(&self, name: &str) -> Option<&str> {
+        match self.get(name)? {
+            Property::String(value) => Some(value),
+            _ => None,
+        }
+    }
+    /// Reads a float property, also accepting ints.
+    pub fn float(&self, name: &str) -> Option<f32> {
+        match self.get(name)? {
+            Property::Float(value) => Some(*value),
+            Property::Int(value) => Some(*value as f32),
+            _ => None,
+        }
+    }
+    pub fn bool(&self, name: &str) -> Option<bool> {
+        match self.get(name)? {
+            Property::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+pub fn parse_map(xml: &str) -> Result<Map, TiledError> {
+    let doc = Document::parse(xml)?;
+    let root = doc.root_element();
+    if !root.has_tag_name("map") {
+        return Err(TiledError::Map(format!(
+            "expected <map>, found <{}>",
+            root.tag_name().name()
+        )));
+    }
+    let map_err = |reason: String| TiledError::Map(reason);
+
+    let width = attr(root, "width").map_err(map_err)?;
+    let height = attr(root, "height").map_err(map_err)?;
+    let mut map = Map {
+        width,
+        height,
+        tile_width: attr(root, "tilewidth").map_err(map_err)?,
+        tile_height: attr(root, "tileheight").map_err(map_err)?,
+        infinite: attr_or(root, "infinite", 0u8).map_err(map_err)? == 1,
+        tilesets: Vec::new(),
+        layers: Vec::new(),
+        object_groups: Vec::new(),
+        next_object_id: attr_or(root, "nextobjectid", 1).map_err(map_err)?,
+    };
+
+    for tileset in root.children().filter(|f| f.has_tag_name("tileset")) {
+        let first_gid = attr(tileset, "firstgid").map_err(TiledError::Tileset)?;
+        map.tilesets.push(match tileset.attribute("source") {
+            Some(source) => MapTileset::External {
+                first_gid,
+                source: source.to_string(),
+            },
+            None => MapTileset::Embedded(tileset_from_node(tileset, first_gid)?),
+        });
+    }
+
+    let group = Group {
+        opacity: 1.0,
+        visible: true,
+        offset: Vec2::ZERO,
+    };
+    parse_layers(root, &group, (width, height), &mut map)?;
+    Ok(map)
+}
+
+pub fn parse_tileset(xml: &str, first_gid: u32) -> Result<Tileset, TiledError> {
+    let doc = Document::parse(xml)?;
+    let root = doc.root_element();
+    if !root.has_tag_name("tileset") {
+        return Err(TiledError::Tileset(format!(
+            "expected <tileset>, found <{}>",
+            root.tag_name().name()
+        )));
+    }
+    tileset_from_node(root, first_gid)
+}
+
+fn tileset_from_node(node: Node, first_gid: u32) -> Result<Tileset, TiledError> {
+    let err = TiledError::Tileset;
+    let mut tiles = HashMap::new();
+    let mut animations = HashMap::new();
+    for tile in node.children().filter(|f| f.has_tag_name("tile")) {
+        let id: u32 = attr(tile, "id").map_err(err)?;
+        let with_id = |reason: String| err(format!("tile {id}: {reason}"));
+        tiles.insert(id, parse_properties(tile).map_err(with_id)?);
+        if let Some(animation) = tile.children().find(|f| f.has_tag_name("animation")) {
+            let frames: Vec<Frame> = animation
+                .children()
+                .filter(|f| f.has_tag_name("frame"))
+                .map(|f| {
+                    Ok(Frame {
+                        tile: attr(f, "tileid")?,
+                        duration: attr(f, "duration")?,
+                    })
+                })
+                .collect::<Result<_, String>>()
+                .map_err(with_id)?;
+            if frames.iter().any(|f| f.duration > 0) {
+                animations.insert(id, frames);
+            }
+        }
+    }
+    let columns = attr(node, "columns").map_err(err)?;
+    // image collection tilesets have a separate image for each tile, instead of a grid
+    if columns == 0 {
+        return Err(err(
+            "image collection tilesets aren't supported, tiles have to be in one image".to_string(),
+        ));
+    }
+    Ok(Tileset {
+        first_gid,
+        name: node.attribute("name").unwrap_or_default().to_string(),
+        tile_width: attr(node, "tilewidth").map_err(err)?,
+        tile_height: attr(node, "tileheight").map_err(err)?,
+        tile_count: attr(node, "tilecount").map_err(err)?,
+        columns,
+        tiles,
+        animations,
+    })
+}
+
+impl Tileset {
+    /// The tile shown in place of tile `id` at `time`, in milliseconds, which is `id` itself
+    /// unless the tile is animated.
+    pub fn tile_at_time(&self, id: u32, time: u32) -> u32 {
+        let Some(frames) = self.animations.get(&id) else {
+            return id;
+        };
+        let mut time = time % frames.iter().map(|f| f.duration).sum::<u32>();
+        for frame in frames {
+            if time < frame.duration {
+                return frame.tile;
+            }
+            time -= frame.duration;
+        }
+        id
+    }
+}
+
+/// Properties inherited from enclosing `<group>` layers.
+struct Group {
+    opacity: f32,
+    visible: bool,
+    offset: Vec2,
+}
+
+fn parse_layers(
+    parent: Node,
+    group: &Group,
+    map_size: (u32, u32),
+    map: &mut Map,
+) -> Result<(), TiledError> {
+    for node in parent.children().filter(|f| f.is_element()) {
+        let name = node.attribute("name").unwrap_or_default().to_string();
+        let err = |reason: String| TiledError::Layer {
+            layer: name.clone(),
+            chunk: None,
+            reason,
+        };
+        let opacity: f32 = attr_or(node, "opacity", 1.0).map_err(err)?;
+        let visible: u8 = attr_or(node, "visible", 1).map_err(err)?;
+        let offset = vec2(
+            attr_or(node, "offsetx", 0.0).map_err(err)?,
+            attr_or(node, "offsety", 0.0).map_err(err)?,
+        );
+        let inner = Group {
+            opacity: group.opacity * opacity,
+            visible: group.visible && visible == 1,
+            offset: group.offset + offset,
+        };
+
+        match node.tag_name().name() {
+            "group" => parse_layers(node, &inner, map_size, map)?,
+            "layer" => {
+                let width = attr_or(node, "width", map_size.0).map_err(err)?;
+                let height = attr_or(node, "height", map_size.1).map_err(err)?;
+                let data = node
+                    .children()
+                    .find(|f| f.has_tag_name("data"))
+                    .ok_or_else(|| err("missing <data>".to_string()))?;
+                map.layers.push(TileLayer {
+                    id: attr(node, "id").map_err(err)?,
+                    opacity: inner.opacity,
+                    visible: inner.visible,
+                    offset: inner.offset,
+                    chunks: parse_data(data, &name, width, height)?,
+                    name,
+                });
+            }
+            "objectgroup" => {
+                let objects = node
+                    .children()
+                    .filter(|f| f.has_tag_name("object"))
+                    .map(|f| parse_object(f, inner.offset))
+                    .collect::<Result<_, _>>()
+                    .map_err(err)?;
+                map.object_groups.push(ObjectGroup {
+                    id: attr(node, "id").map_err(err)?,
+                    visible: inner.visible,
+                    objects,
+                    name,
+                });
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn parse_object(node: Node, offset: Vec2) -> Result<Object, String> {
+    let id = attr(node, "id")?;
+    let with_id = |reason: String| format!("object {id}: {reason}");
+    let kind = node
+        .attribute("type")
+        .or(node.attribute("class"))
+        .unwrap_or_default();
+    Ok(Object {
+        id,
+        name: node.attribute("name").unwrap_or_default().to_string(),
+        kind: kind.to_string(),
+        pos: vec2(
+            attr(node, "x").map_err(with_id)?,
+            attr(node, "y").map_err(with_id)?,
+        ) + offset,
+        size: vec2(
+            attr_or(node, "width", 0.0).map_err(with_id)?,
+            attr_or(node, "height", 0.0).map_err(with_id)?,
+        ),
+        properties: parse_properties(node).map_err(with_id)?,
+    })
+}
+
+/// Reads the `<properties>` child of `node`, if there is one.
+pub fn parse_properties(node: Node) -> Result<Properties, String> {
+    let mut properties = Properties::default();
+    let Some(list) = node.children().find(|f| f.has_tag_name("properties")) else {
+        return Ok(properties);
+    };
+    for property in list.children().filter(|f| f.has_tag_name("property")) {
+        let name: String = attr(property, "name")?;
+        // multiline strings are stored as text instead of in the value attribute
+        let raw = property
+            .attribute("value")
+            .or(property.text())
+            .unwrap_or_default();
+        let invalid = || format!("invalid value \"{raw}\" for property \"{name}\"");
+        let value = match property.attribute("type").unwrap_or("string") {
+            "int" | "object" => Property::Int(raw.parse().map_err(|_| invalid())?),
+            "float" => Property::Float(raw.parse().map_err(|_| invalid())?),
+            "bool" => Property::Bool(raw.parse().map_err(|_| invalid())?),
+            // nested class properties aren't supported
+            "class" => continue,
+            _ => Property::String(raw.to_string()),
+        };
+        properties.0.insert(name, value);
+    }
+    Ok(properties)
+}
+
+fn parse_data(
+    data: Node,
+    layer: &str,
+    width: u32,
+    height: u32,
+) -> Result<Vec<TileChunk>, TiledError> {
+    let encoding = data.attribute("encoding");
+    let compression = data.attribute("compression");
+
+    let chunk_nodes: Vec<Node> = data
+        .children()
+        .filter(|f| f.has_tag_name("chunk"))
+        .collect();
+
+    // finite map, all tiles are stored directly in <data>
+    if chunk_nodes.is_empty() {
+        let err = |reason| TiledError::Layer {
+            layer: layer.to_string(),
+            chunk: None,
+            reason,
+        };
+        let count = tile_count(width, height).map_err(err)?;
+        let tiles = decode_tiles(data, encoding, compression, count).map_err(err)?;
+        return Ok(vec![TileChunk {
+            x: 0,
+            y: 0,
+            width,
+            height,
+            tiles,
+        }]);
+    }
+
+    let mut chunks = Vec::with_capacity(chunk_nodes.len());
+    for node in chunk_nodes {
+        let x: Option<i32> = node.attribute("x").and_then(|f| f.parse().ok());
+        let y: Option<i32> = node.attribute("y").and_then(|f| f.parse().ok());
+        let (Some(x), Some(y)) = (x, y) else {
+            return Err(TiledError::Layer {
+                layer: layer.to_string(),
+                chunk: None,
+                reason: "chunk is missing a valid x or y".to_string(),
+            });
+        };
+        let err = |reason: String| TiledError::Layer {
+            layer: layer.to_string(),
+            chunk: Some((x, y)),
+            reason,
+        };
+        let width = attr(node, "width").map_err(err)?;
+        let height = attr(node, "height").map_err(err)?;
+        let count = tile_count(width, height).map_err(err)?;
+        let tiles = decode_tiles(node, encoding, compression, count).map_err(err)?;
+        chunks.push(TileChunk {
+            x,
+            y,
+            width,
+            height,
+            tiles,
+        });
+    }
+    Ok(chunks)
+}
+
+fn decode_tiles(
+    node: Node,
+    encoding: Option<&str>,
+    compression: Option<&str>,
+    expected: usize,
+) -> Result<Vec<u32>, String> {
+    let tiles: Vec<u32> = match encoding {
+        Some("csv") => node
+            .text()
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|f| !f.is_empty())
+            .map(|f| f.parse().map_err(|_| format!("invalid tile \"{f}\"")))
+            .collect::<Result<_, _>>()?,
+        Some("base64") => {
+            let text: String = node
+                .text()
+                .unwrap_or_default()
+                .chars()
+                .filter(|f| !f.is_whitespace())
+                .collect();
+            let raw = base64::engine::general_purpose::STANDARD
+                .decode(text)
+                .map_err(|err| format!("invalid base64: {err}"))?;
+            let bytes = decompress(&raw, compression)?;
+            if bytes.len() % 4 != 0 {
+                return Err(format!("data length {} isn't a multiple of 4", bytes.len()));
+            }
+            bytes
+                .chunks_exact(4)
+                .map(|f| u32::from_le_bytes([f[0], f[1], f[2], f[3]]))
+                .collect()
+        }
+        None => node
+            .children()
+            .filter(|f| f.has_tag_name("tile"))
+            .map(|f| attr_or(f, "gid", 0))
+            .collect::<Result<_, _>>()?,
+        Some(other) => return Err(format!("unsupported encoding \"{other}\"")),
+    };
+    if tiles.len() != expected {
+        return Err(format!("expected {expected} tiles, found {}", tiles.len()));
+    }
+    Ok(tiles)
+}
+
+fn decompress(raw: &[u8], compression: Option<&str>) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+    let result = match compression {
+        None | Some("") => return Ok(raw.to_vec()),
+        Some("gzip") => GzDecoder::new(raw).read_to_end(&mut bytes),
+        Some("zlib") => ZlibDecoder::new(raw).read_to_end(&mut bytes),
+        Some(other) => return Err(format!("unsupported compression \"{other}\"")),
+    };
+    result.map_err(|err| format!("couldn't decompress data: {err}"))?;
+    Ok(bytes)
+}
+
+/// Changes to write into a map with `update_map`.
+#[derive(Default)]
+pub struct MapUpdate<'a> {
+    /// New tiles of tile layers, by layer name. Layers that aren't listed are left as they are.
+    pub layers: Vec<(&'a str, Vec<TileChunk>)>,
+    /// New top left corners of objects, by id. Include the offset of the object layer, like
+    /// `Object::pos`.
+    pub moved_objects: Vec<(u32, Vec2)>,
+    /// Every object of a type, by type, as their ids and where they are. Objects already in the
+    /// map are moved, the rest are added, and objects of the type that aren't listed are removed.
+    pub synced_objects: Vec<(&'a str, Vec<(u32, Rect)>)>,
+}
+
+/// Writes `update` into the map `xml`, keeping everything else in the file as it was.
+pub fn update_map(xml: &str, update: &MapUpdate) -> Result<String, TiledError> {
+    let doc = Document::parse(xml)?;
+    let root = doc.root_element();
+    if !root.has_tag_name("map") {
+        return Err(TiledError::Map(format!(
+            "expected <map>, found <{}>",
+            root.tag_name().name()
+        )));
+    }
+    let infinite = root.attribute("infinite") == Some("1");
+    let map_size = (
+        attr(root, "width").map_err(TiledError::Map)?,
+        attr(root, "height").map_err(TiledError::Map)?,
+    );
+    // replacements of ranges of the file, which mustn't overlap
+    let mut edits: Vec<(std::ops::Range<usize>, String)> = Vec::new();
+
+    for (name, chunks) in &update.layers {
+        let err = |reason: String| TiledError::Layer {
+            layer: name.to_string(),
+            chunk: None,
+            reason,
+        };
+        let Some(layer) = root
+            .descendants()
+            .find(|f| f.has_tag_name("layer") && f.attribute("name") == Some(name))
+        else {
+            if chunks.iter().all(|f| f.tiles.iter().all(|f| *f == 0)) {
+                continue;
+            }
+            return Err(TiledError::Map(format!("map has no layer \"{name}\"")));
+        };
+        let data = layer
+            .children()
+            .find(|f| f.has_tag_name("data"))
+            .ok_or_else(|| err("missing <data>".to_string()))?;
+        let text = if infinite {
+            write_chunks(chunks)
+        } else {
+            let width = attr_or(layer, "width", map_size.0).map_err(err)?;
+            let height = attr_or(layer, "height", map_size.1).map_err(err)?;
+            write_grid(chunks, width, height).map_err(err)?
+        };
+        edits.push((
+            data.range(),
+            format!("<data encoding=\"csv\">\n{text}  </data>"),
+        ));
+    }
+
+    let objects: Vec<Node> = root
+        .descendants()
+        .filter(|f| f.has_tag_name("object"))
+        .collect();
+    let has_id = |object: &Node, id: u32| object.attribute("id") == Some(&id.to_string());
+    // edits moving `object` so its top left corner is at `pos`
+    let move_object = |object: &Node, id: u32, pos: Vec2| {
+        let pos = pos - object_offset(*object)?;
+        [("x", pos.x), ("y", pos.y)]
+            .into_iter()
+            .map(|(name, value)| {
+                let attribute = object
+                    .attribute_node(name)
+                    .ok_or_else(|| TiledError::Map(format!("object {id} is missing \"{name}\"")))?;
+                Ok((attribute.range_value(), value.to_string()))
+            })
+            .collect::<Result<Vec<_>, TiledError>>()
+    };
+    for (id, pos) in &update.moved_objects {
+        let object = objects
+            .iter()
+            .find(|f| has_id(f, *id))
+            .ok_or_else(|| TiledError::Map(format!("map has no object {id}")))?;
+        edits.extend(move_object(object, *id, *pos)?);
+    }
+
+    let mut next_id: u32 = attr(root, "nextobjectid").map_err(TiledError::Map)?;
+    for (kind, synced) in &update.synced_objects {
+        let is_kind = |f: &&Node| f.attribute("type").or(f.attribute("class")) == Some(kind);
+        let old: Vec<&Node> = objects.iter().filter(is_kind).collect();
+        let group = old
+            .first()
+            .and_then(|f| f.parent())
+            .or_else(|| root.descendants().find(|f| f.has_tag_name("objectgroup")))
+            .ok_or_else(|| TiledError::Map("map has no object layer".to_string()))?;
+        for object in &old {
+            if let Some((id, rect)) = synced.iter().find(|(id, _)| has_id(object, *id)) {
+                edits.extend(move_object(object, *id, rect.point())?);
+                continue;
+            }
+            // along with the indentation before it
+            let range = object.range();
+            let start = xml[..range.start].trim_end_matches([' ', '\t']).len();
+            let rest = &xml[range.end..];
+            let end = rest
+                .find('\n')
+                .filter(|f| rest[..*f].trim().is_empty())
+                .map_or(range.end, |f| range.end + f + 1);
+            edits.push((start..end, String::new()));
+        }
+        let offset = object_offset(group)?;
+        let mut text = String::new();
+        let added = synced
+            .iter()
+            .filter(|(id, _)| !old.iter().any(|f| has_id(f, *id)));
+        for (id, rect) in added {
+            let pos = rect.point() - offset;
+            text += &format!(
+                "  <object id=\"{id}\" type=\"{kind}\" x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\"/>\n",
+                pos.x, pos.y, rect.w, rect.h
+            );
+            next_id = next_id.max(id + 1);
+        }
+        let range = group.range();
+        if text.is_empty() {
+            continue;
+        } else if xml[range.clone()].ends_with("/>") {
+            edits.push((
+                range.end - 2..range.end,
+                format!(">\n{text} </objectgroup>"),
+            ));
+        } else {
+            let end = range.end - "</objectgroup>".len();
+            edits.push((end..end, text));
+        }
+    }
+    if let Some(attribute) = root.attribute_node("nextobjectid") {
+        edits.push((attribute.range_value(), next_id.to_string()));
+    }
+
+    edits.sort_by_key(|(range, _)| std::cmp::Reverse(range.start));
+    let mut out = xml.to_string();
+    for (range, text) in edits {
+        out.replace_range(range, &text);
+    }
+    Ok(out)
+}
+
+/// Offset in pixels of everything in `node`, from the layers it's in.
+fn object_offset(node: Node) -> Result<Vec2, TiledError> {
+    let mut offset = Vec2::ZERO;
+    for node in node.ancestors().filter(|f| f.is_element()) {
+        offset += vec2(
+            attr_or(node, "offsetx", 0.0).map_err(TiledError::Map)?,
+            attr_or(node, "offsety", 0.0).map_err(TiledError::Map)?,
+        );
+    }
+    Ok(offset)
+}
+
+/// Number of tiles in an area of `width` by `height` tiles.
+fn tile_count(width: u32, height: u32) -> Result<usize, String> {
+    (width as usize)
+        .checked_mul(height as usize)
+        .ok_or_else(|| format!("{width} by {height} tiles is too many"))
+}
+
+/// CSV data of an infinite map, leaving out empty chunks like Tiled does.
+fn write_chunks(chunks: &[TileChunk]) -> String {
+    let mut text = String::new();
+    for chunk in chunks.iter().filter(|f| f.tiles.iter().any(|f| *f != 0)) {
+        text += &format!(
+            "   <chunk x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\">\n",
+            chunk.x, chunk.y, chunk.width, chunk.height
+        );
+        text += &write_csv(&chunk.tiles, chunk.width);
+        text += "   </chunk>\n";
+    }
+    text
+}
+
+/// CSV data of a finite map of `width` by `height` tiles.
+fn write_grid(chunks: &[TileChunk], width: u32, height: u32) -> Result<String, String> {
+    let mut tiles = vec![0; tile_count(width, height)?];
+    for chunk in chunks {
+        for (index, tile) in chunk.tiles.iter().enumerate().filter(|(_, f)| **f != 0) {
+            let x = chunk.x + (index as u32 % chunk.width) as i32;
+            let y = chunk.y + (index as u32 / chunk.width) as i32;
+            if !(0..width as i32).contains(&x) || !(0..height as i32).contains(&y) {
+                return Err(format!("tile at ({x}, {y}) is outside of the map"));
+            }
+            tiles[(x + y * width as i32) as usize] = *tile;
+        }
+    }
+    Ok(write_csv(&tiles, width))
+}
+
+/// Tiles as rows of comma separated gids, one row per line.
+fn write_csv(tiles: &[u32], width: u32) -> String {
+    let rows: Vec<String> = tiles
+        .chunks(width as usize)
+        .map(|row| row.iter().map(u32::to_string).collect::<Vec<_>>().join(","))
+        .collect();
+    rows.join(",\n") + "\n"
+}
+
+fn attr<T: std::str::FromStr>(node: Node, name: &str) -> Result<T, String> {
+    let value = node
+        .attribute(name)
+        .ok_or_else(|| format!("<{}> is missing \"{name}\"", node.tag_name().name()))?;
+    value
+        .parse()
+        .map_err(|_| format!("invalid {name} \"{value}\""))
+}
+
+fn attr_or<T: std::str::FromStr>(node: Node, name: &str, default: T) -> Result<T, String> {
+    match node.attribute(name) {
+        Some(_) => attr(node, name),
+        None => Ok(default),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use flate2::{
+        Compression,
+        write::{GzEncoder, ZlibEncoder},
+    };
+
+    use super::*;
+
+    /// A 2x2 map with one layer holding `data`.
+    fn map_with_data(data: &str) -> String {
+        format!(
+            r#"<map width="2" height="2" tilewidth="8" tileheight="8">
+                <layer id="1" name="tiles">{data}</layer>
+            </map>"#
+        )
+    }
+
+    fn tiles(data: &str) -> Vec<u32> {
+        let map = parse_map(&map_with_data(data)).unwrap();
+        map.layers[0].chunks[0].tiles.clone()
+    }
+
+    fn error(data: &str) -> String {
+        match parse_map(&map_with_data(data)) {
+            Ok(_) => panic!("expected an error"),
+            Err(err) => err.to_string(),
+        }
+    }
+
+    /// Base64 of `tiles`, compressed with `compress`.
+    fn encode(tiles: &[u32], compress: impl FnOnce(&[u8]) -> Vec<u8>) -> String {
+        let bytes: Vec<u8> = tiles.iter().flat_map(|f| f.to_le_bytes()).collect();
+        base64::engine::general_purpose::STANDARD.encode(compress(&bytes))
+    }
+
+    #[test]
+    fn decodes_every_encoding() {
+        let expected = [1, 0, 3, 40];
+        assert_eq!(
+            tiles(
+                r#"<data encoding="csv">1,0,
+            3,40</data>"#
+            ),
+            expected
+        );
+        assert_eq!(
+            tiles(r#"<data><tile gid="1"/><tile/><tile gid="3"/><tile gid="40"/></data>"#),
+            expected
+        );
+
+        let base64 = encode(&expected, <[u8]>::to_vec);
+        let data = format!(r#"<data encoding="base64">{base64}</data>"#);
+        assert_eq!(tiles(&data), expected);
+
+        let gzip = encode(&expected, |f| {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(f).unwrap();
+            encoder.finish().unwrap()
+        });
+        let data = format!(r#"<data encoding="base64" compression="gzip">{gzip}</data>"#);
+        assert_eq!(tiles(&data), expected);
+
+        let zlib = encode(&expected, |f| {
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(f).unwrap();
+            encoder.finish().unwrap()
+        });
+        let data = format!(r#"<data encoding="base64" compression="zlib">{zlib}</data>"#);
+        assert_eq!(tiles(&data), expected);
+    }
+
+    #[test]
+    fn reads_chunks_of_infinite_maps() {
+        let map = parse_map(
+            r#"<map width="2" height="2" tilewidth="8" tileheight="8" infinite="1">
+                <layer id="1" name="tiles">
+                    <data encoding="csv">
+                        <chunk x="-16" y="0" width="2" height="1">5,6</chunk>
+                        <chunk x="0" y="16" width="1" height="1">7</chunk>
+                    </data>
+                </layer>
+            </map>"#,
+        )
+        .unwrap();
+        assert!(map.infinite);
+        let chunks = &map.layers[0].chunks;
+        assert_eq!((chunks[0].x, chunks[0].y, chunks[0].width), (-16, 0, 2));
+        assert_eq!(chunks[0].tiles, [5, 6]);
+        assert_eq!((chunks[1].x, chunks[1].y), (0, 16));
+        assert_eq!(chunks[1].tiles, [7]);
+    }
+
+    #[test]
+    fn flattens_groups() {
+        let map = parse_map(
+            r#"<map width="1" height="1" tilewidth="8" tileheight="8">
+                <group name="outer" opacity="0.5" offsetx="8" offsety="-4">
+                    <group name="inner" visible="0" offsetx="2">
+                        <layer id="1" name="hidden" opacity="0.5">
+                            <data encoding="csv">1</data>
+                        </layer>
+                    </group>
+                    <objectgroup id="2" name="objects">
+                        <object id="3" name="spawn" x="10" y="20"/>
+                    </objectgroup>
+                </group>
+                <layer id="4" name="plain"><data encoding="csv">2</data></layer>
+            </map>"#,
+        )
+        .unwrap();
+
+        let hidden = map.layer("hidden").unwrap();
+        assert_eq!(hidden.opacity, 0.25);
+        assert!(!hidden.visible);
+        assert_eq!(hidden.offset, vec2(10.0, -4.0));
+        let plain = map.layer("plain").unwrap();
+        assert_eq!(
+            (plain.opacity, plain.visible, plain.offset),
+            (1.0, true, Vec2::ZERO)
+        );
+
+        let objects: Vec<&Object> = map.objects().collect();
+        assert_eq!(objects.len(), 1);
+        assert_eq!(objects[0].name, "spawn");
+        // object positions include the offset of the layers they're in
+        assert_eq!(objects[0].pos, vec2(18.0, 16.0));
+    }
+
+    #[test]
+    fn describes_errors() {
+        assert_eq!(
+            error(r#"<data encoding="csv">1,2,3</data>"#),
+            "layer \"tiles\": expected 4 tiles, found 3"
+        );
+        assert_eq!(
+            error(r#"<data encoding="csv">1,x,3,4</data>"#),
+            "layer \"tiles\": invalid tile \"x\""
+        );
+        assert_eq!(
+            error(r#"<data encoding="hex">00</data>"#),
+            "layer \"tiles\": unsupported encoding \"hex\""
+        );
+        assert_eq!(
+            error(r#"<data encoding="base64" compression="zstd">AAAA</data>"#),
+            "layer \"tiles\": unsupported compression \"zstd\""
+        );
+        assert_eq!(
+            error(r#"<data encoding="base64">AAAA</data>"#),
+            "layer \"tiles\": data length 3 isn't a multiple of 4"
+        );
+
+        let chunk = map_with_data(
+            r#"<data encoding="csv"><chunk x="16" y="-16" width="1" height="1">1,2</chunk></data>"#,
+        );
+        assert_eq!(
+            parse_map(&chunk).err().unwrap().to_string(),
+            "layer \"tiles\", chunk (16, -16): expected 1 tiles, found 2"
+        );
+        let huge = map_with_data(
+            r#"<data encoding="csv"><chunk x="0" y="0" width="65536" height="65536">1</chunk></data>"#,
+        );
+        assert_eq!(
+            parse_map(&huge).err().unwrap().to_string(),
+            "layer \"tiles\", chunk (0, 0): expected 4294967296 tiles, found 1"
+        );
+        let tilesets = r#"<map width="1" height="1" tilewidth="8" tileheight="8">
+            <tileset firstgid="1" source="a.tsx"/>
+            <tileset firstgid="5" source="b.tsx"/>
+        </map>"#;
+        assert_eq!(
+            parse_map(tilesets)
+                .unwrap()
+                .tileset(|_| None)
+                .err()
+                .unwrap()
+                .to_string(),
+            "map: only one tileset is supported, found 2"
+        );
+        assert_eq!(
+            parse_map("<tileset/>").err().unwrap().to_string(),
+            "map: expected <map>, found <tileset>"
+        );
+        let images =
+            r#"<tileset name="images" tilewidth="8" tileheight="8" tilecount="2" columns="0"/>"#;
+        assert_eq!(
+            parse_tileset(images, 1).err().unwrap().to_string(),
+            "tileset: image collection tilesets aren't supported, tiles have to be in one image"
+        );
+    }
+}
@@ -5,13 +5,14 @@ use crate::{assets::Assets, player::Player};
 pub const SCREEN_WIDTH: f32 = 256.0;
 pub const SCREEN_HEIGHT: f32 = 144.0;
 
-pub const MAX_VELOCITY: f32 = 90.0;
-pub const GROUND_FRICTION: f32 = 0.21 * 60.0;
-pub const AIR_DRAG: f32 = 0.01 * 60.0;
-pub const GRAVITY: f32 = 0.25 * 3600.0;
-
 pub const PUMPKIN_PICKUP_DIST: f32 = 9.0;
 
+/// The game is simulated at a fixed rate, independent of the frame rate.
+pub const TICK_RATE: f32 = 60.0;
+pub const TICK: f32 = 1.0 / TICK_RATE;
+/// Longest frame that is simulated in full. Longer frames, like after a hitch, slow the game down instead.
+pub const MAX_FRAME_TIME: f32 = 0.25;
+
 pub fn create_camera(w: f32, h: f32) -> Camera2D {
     let rt = render_target(w as u32, h as u32);
     rt.texture.set_filter(FilterMode::Nearest);
@@ -23,42 +24,36 @@ pub fn create_camera(w: f32, h: f32) -> Camera2D {
     }
 }
 
-pub fn show_tooltip(text: &str, assets: &Assets, player: &Player) -> bool {
-    let padding = 2.0;
+/// Draws `text` above the player.
+pub fn show_tooltip(text: &str, assets: &Assets, player: &Player) {
     let margin = 2.0;
 
-    let width = text.len() as f32 * 4.0 + padding * 2.0;
-    let height = 5.0 + padding * 2.0;
-    let x = (player.camera_pos.x - width / 2.0 + 4.0).floor();
-    let y = (player.camera_pos.y - height - margin + SCREEN_HEIGHT / 2.0).floor();
-    draw_rectangle(x, y, width, height, Color::from_hex(0x3b1725));
+    let size = text_box_size(text);
+    let x = (player.camera_pos.x - size.x / 2.0 + 4.0).floor();
+    let y = (player.camera_pos.y - size.y - margin + SCREEN_HEIGHT / 2.0).floor();
+    draw_text_box(text, assets, vec2(x, y) + size / 2.0);
+}
+
+const TEXT_BOX_PADDING: f32 = 2.0;
+
+pub fn text_box_size(text: &str) -> Vec2 {
+    let columns = text.lines().map(str::len).max().unwrap_or(0);
+    let lines = text.lines().count();
+    vec2(columns as f32 * 4.0, lines as f32 * 5.0) + TEXT_BOX_PADDING * 2.0
+}
+
+/// Draws `text` in a box centered on `center`.
+pub fn draw_text_box(text: &str, assets: &Assets, center: Vec2) {
+    let size = text_box_size(text);
+    let x = (center.x - size.x / 2.0).floor();
+    let y = (center.y - size.y / 2.0).floor();
+    draw_rectangle(x, y, size.x, size.y, Color::from_hex(0x3b1725));
     draw_rectangle(
         x + 1.0,
         y + 1.0,
-        width - 2.0,
-        height - 2.0,
+        size.x - 2.0,
+        size.y - 2.0,
         Color::from_hex(0xfffc40),
     );
-    assets.draw_text(text, x + padding, y + padding);
-    if is_key_pressed(KeyCode::E) {
-        return true;
-    }
-    false
-}
-
-pub fn get_input_axis() -> Vec2 {
-    let mut i = Vec2::ZERO;
-    if is_key_down(KeyCode::A) {
-        i.x -= 1.0;
-    }
-    if is_key_down(KeyCode::D) {
-        i.x += 1.0;
-    }
-    if is_key_down(KeyCode::W) {
-        i.y -= 1.0;
-    }
-    if is_key_down(KeyCode::S) {
-        i.y += 1.0;
-    }
-    if i.length() == 0.0 { i } else { i.normalize() }
+    assets.draw_text(text, x + TEXT_BOX_PADDING, y + TEXT_BOX_PADDING);
 }
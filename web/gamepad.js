@@ -0,0 +1,39 @@
+// Reads controllers through the browser's Gamepad API. Used by `src/gamepad.rs` on wasm builds.
+(function () {
+    "use strict";
+
+    // controllers without the standard mapping have their buttons in an unknown order
+    function gamepads() {
+        return Array.from(navigator.getGamepads ? navigator.getGamepads() : [])
+            .filter(function (pad) { return pad && pad.connected && pad.mapping === "standard"; });
+    }
+
+    function register_plugin(importObject) {
+        importObject.env.pumpkin_gamepad_count = function () {
+            return gamepads().length;
+        };
+        importObject.env.pumpkin_gamepad_buttons = function () {
+            var buttons = 0;
+            gamepads().forEach(function (pad) {
+                pad.buttons.forEach(function (button, index) {
+                    if (button.pressed && index < 16) {
+                        buttons |= 1 << index;
+                    }
+                });
+            });
+            return buttons;
+        };
+        importObject.env.pumpkin_gamepad_axis = function (axis) {
+            var value = 0;
+            gamepads().forEach(function (pad) {
+                var pad_value = pad.axes[axis] || 0;
+                if (Math.abs(pad_value) > Math.abs(value)) {
+                    value = pad_value;
+                }
+            });
+            return value;
+        };
+    }
+
+    miniquad_add_plugin({ register_plugin, version: 1, name: "pumpkin_gamepad" });
+}());
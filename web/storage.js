@@ -0,0 +1,28 @@
+// Lets the game keep saves and settings in localStorage. Used by `src/storage.rs` on wasm builds.
+(function () {
+    "use strict";
+
+    function string_at(ptr, len) {
+        return new TextDecoder().decode(new Uint8Array(wasm_memory.buffer, ptr, len));
+    }
+
+    function register_plugin(importObject) {
+        importObject.env.pumpkin_storage_set = function (key, key_len, value, value_len) {
+            localStorage.setItem(string_at(key, key_len), string_at(value, value_len));
+        };
+        importObject.env.pumpkin_storage_len = function (key, key_len) {
+            var value = localStorage.getItem(string_at(key, key_len));
+            if (value === null) {
+                return -1;
+            }
+            return new TextEncoder().encode(value).length;
+        };
+        importObject.env.pumpkin_storage_get = function (key, key_len, out, out_len) {
+            var value = localStorage.getItem(string_at(key, key_len)) || "";
+            var bytes = new TextEncoder().encode(value).subarray(0, out_len);
+            new Uint8Array(wasm_memory.buffer, out, out_len).set(bytes);
+        };
+    }
+
+    miniquad_add_plugin({ register_plugin, version: 1, name: "pumpkin_storage" });
+}());